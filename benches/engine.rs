@@ -0,0 +1,121 @@
+// The engine's hot paths on a HARD board and a 500x500 one with the same
+// density of rusts, so a slowdown in any of them shows up as a regression:
+//
+//     cargo bench --bench engine
+//     cargo bench --bench engine -- --save-baseline main    # on main
+//     cargo bench --bench engine -- --baseline main         # on a branch
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use minesweeper::board::{Board, GameEvent, EASY, HARD, MEDIUM};
+use minesweeper::frame;
+use minesweeper::solver;
+use minesweeper::variant::Variant;
+
+// HARD's density of rusts, 500 cells a side
+const HUGE: (usize, usize) = (28_120, 500);
+const SIZES: [(&str, (usize, usize)); 2] = [("hard", HARD), ("500x500", HUGE)];
+
+fn board(difficulty: (usize, usize)) -> Board {
+    let mut board = Board::from_seed(difficulty, Variant::default(), 7);
+    board.calculate_rust_count();
+    board
+}
+
+// a game just after a safe first click in the middle
+fn opened(difficulty: (usize, usize)) -> Board {
+    let mut board = board(difficulty);
+    let middle = difficulty.1 / 2;
+    board.clear_rusts_at(middle, middle);
+    board.reveal(middle, middle);
+    board
+}
+
+fn cells(difficulty: (usize, usize)) -> Throughput {
+    Throughput::Elements(difficulty.1.pow(2) as u64)
+}
+
+fn generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generation");
+    for &(name, difficulty) in &SIZES {
+        group.throughput(cells(difficulty));
+        group.bench_function(name, |b| {
+            b.iter(|| Board::new(difficulty, Variant::default()))
+        });
+    }
+    group.finish();
+}
+
+fn counting(c: &mut Criterion) {
+    let mut group = c.benchmark_group("calculate_rust_count");
+    for &(name, difficulty) in &SIZES {
+        let mut board = board(difficulty);
+        group.throughput(cells(difficulty));
+        group.bench_function(name, |b| b.iter(|| board.calculate_rust_count()));
+    }
+    group.finish();
+}
+
+// one rust in a hundred cells, so a click opens up most of the board
+fn flood_fill(c: &mut Criterion) {
+    let mut group = c.benchmark_group("flood_fill_sparse");
+    for &(name, size) in &[("hard", HARD.1), ("500x500", HUGE.1)] {
+        let difficulty = (size * size / 100, size);
+        let board = board(difficulty);
+        let (x, y) = (0..size * size)
+            .map(|n| (n / size, n % size))
+            .find(|&(x, y)| {
+                let cell = board.cell(x, y);
+                !cell.is_rust() && cell.rust_count() == 0
+            })
+            .unwrap();
+        group.throughput(cells(difficulty));
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || board.clone(),
+                |mut board| board.flood_fill(x, y),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn solver(c: &mut Criterion) {
+    let mut group = c.benchmark_group("solver");
+    group.sample_size(20);
+    for &(name, difficulty) in &[
+        ("easy", EASY),
+        ("medium", MEDIUM),
+        ("hard", HARD),
+        ("500x500", HUGE),
+    ] {
+        let board = opened(difficulty);
+        group.bench_with_input(BenchmarkId::new("analyze", name), &board, |b, board| {
+            b.iter(|| solver::analyze(board))
+        });
+        group.bench_with_input(BenchmarkId::new("hint", name), &board, |b, board| {
+            b.iter(|| solver::hint(board))
+        });
+    }
+    group.finish();
+}
+
+// what the game works out for every frame it draws, while playing and
+// while the rusts pop up after a loss
+fn frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    for &(name, difficulty) in &SIZES {
+        let board = opened(difficulty);
+        group.throughput(cells(difficulty));
+        group.bench_with_input(BenchmarkId::new("playing", name), &board, |b, board| {
+            b.iter(|| frame::prepare(board, None, 0.0))
+        });
+        let lost = Some(GameEvent::Failed { x: 0, y: 0 });
+        group.bench_with_input(BenchmarkId::new("lost", name), &board, |b, board| {
+            b.iter(|| frame::prepare(board, lost, 1.0))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, generation, counting, flood_fill, solver, frames);
+criterion_main!(benches);
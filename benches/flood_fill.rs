@@ -0,0 +1,29 @@
+// Flood fill opening empty square boards of growing size. Every cell is
+// visited once, so the time per cell (the throughput criterion reports)
+// should stay flat from the smallest board to the largest.
+//
+//     cargo bench --bench flood_fill
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use minesweeper::board::Board;
+use minesweeper::variant::Variant;
+
+fn flood_fill(c: &mut Criterion) {
+    let mut group = c.benchmark_group("flood_fill");
+    group.sample_size(10);
+    for &size in &[125, 250, 500, 1000] {
+        let mut board = Board::from_seed((0, size), Variant::default(), 1);
+        board.calculate_rust_count();
+        group.throughput(Throughput::Elements((size * size) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &board, |b, board| {
+            b.iter_batched(
+                || board.clone(),
+                |mut board| board.flood_fill(size / 2, size / 2),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, flood_fill);
+criterion_main!(benches);
@@ -0,0 +1,125 @@
+// Headless benchmark for the bots in `minesweeper::player`. Plays the same
+// seeded games with every bot on every difficulty and prints how they did.
+//
+//     cargo run --release --bin harness -- --games 500 --seed 42
+//
+// With `--puzzles` it instead checks that every bundled puzzle can be solved
+// by logic alone, exiting with an error if one can't.
+use minesweeper::board::{Board, EASY, HARD, MEDIUM};
+use minesweeper::player::{BoardView, Move, Player, RandomBot, SolverBot};
+use minesweeper::puzzle::{self, PUZZLES};
+use minesweeper::solver;
+use minesweeper::variant::Variant;
+use rand::prelude::*;
+use std::env;
+use std::time::{Duration, Instant};
+
+struct GameReport {
+    won: bool,
+    // time spent in the bot's `next_move`, not dealing or checking guesses
+    seconds: f64,
+    // reveals of cells the solver could not prove safe at the time
+    guesses: usize,
+}
+
+fn play(player: &mut dyn Player, difficulty: (usize, usize), seed: u64) -> GameReport {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut board = Board::generate(difficulty, Variant::default(), &mut rng);
+    board.calculate_rust_count();
+    let mut first_move = true;
+    let mut guesses = 0;
+    let mut thinking = Duration::ZERO;
+    // every cell revealed or flagged a few times over means the bot is stuck
+    let move_limit = 4 * difficulty.1.pow(2);
+
+    for _ in 0..move_limit {
+        let start = Instant::now();
+        let next = player.next_move(&BoardView::new(&board));
+        thinking += start.elapsed();
+        if let Move::Reveal { x, y } = next {
+            // same as the game: the first click never hits a rust
+            if first_move {
+                while board.cell(x, y).is_rust() {
+                    board = Board::generate(difficulty, Variant::default(), &mut rng);
+                    board.calculate_rust_count();
+                }
+                first_move = false;
+            }
+            if board.cell(x, y).is_hidden() && !solver::analyze(&board).safe.contains(&(x, y)) {
+                guesses += 1;
+            }
+        }
+        if next.apply(&mut board).is_some() {
+            break;
+        }
+    }
+
+    GameReport {
+        won: board.revealed_safe() == board.safe_cells(),
+        seconds: thinking.as_secs_f64(),
+        guesses,
+    }
+}
+
+fn check_puzzles() {
+    let mut broken = 0;
+    for p in PUZZLES {
+        match puzzle::validate(p) {
+            Ok(moves) => println!("{:<16} ok in {} moves", p.name, moves),
+            Err(e) => {
+                println!("{:<16} {}", p.name, e);
+                broken += 1;
+            }
+        }
+    }
+    if broken > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let mut games = 100;
+    let mut seed = 0;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--puzzles" {
+            check_puzzles();
+            return;
+        }
+        let value = args.next().and_then(|v| v.parse().ok());
+        match (arg.as_str(), value) {
+            ("--games", Some(n)) => games = n,
+            ("--seed", Some(n)) => seed = n,
+            _ => {
+                eprintln!("usage: harness [--games N] [--seed S] | --puzzles");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    println!(
+        "{:<8} {:<8} {:>8} {:>12} {:>10}",
+        "level", "bot", "win rate", "avg time ms", "avg guess"
+    );
+    for &(label, difficulty) in &[("easy", EASY), ("medium", MEDIUM), ("hard", HARD)] {
+        let mut bots: Vec<Box<dyn Player>> =
+            vec![Box::new(RandomBot::new(seed)), Box::new(SolverBot)];
+        for bot in bots.iter_mut() {
+            let reports: Vec<GameReport> = (0..games)
+                .map(|game| play(bot.as_mut(), difficulty, seed + game))
+                .collect();
+            let n = reports.len().max(1) as f64;
+            let wins = reports.iter().filter(|r| r.won).count() as f64;
+            let seconds: f64 = reports.iter().map(|r| r.seconds).sum();
+            let guesses: usize = reports.iter().map(|r| r.guesses).sum();
+            println!(
+                "{:<8} {:<8} {:>7.1}% {:>12.2} {:>10.2}",
+                label,
+                bot.name(),
+                100.0 * wins / n,
+                1000.0 * seconds / n,
+                guesses as f64 / n
+            );
+        }
+    }
+}
@@ -0,0 +1,31 @@
+// JSON-RPC on stdin and stdout, so scripts in any language can play. See
+// `minesweeper::rpc` for the methods.
+//
+//     echo '{"jsonrpc": "2.0", "id": 1, "method": "new_game"}' | cargo run --bin rpc
+use minesweeper::rpc::Session;
+use std::io::{self, BufRead, Write};
+
+fn main() {
+    let mut session = Session::new();
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        for reply in session.handle(&line) {
+            // the other end has gone away
+            if writeln!(out, "{}", reply)
+                .and_then(|_| out.flush())
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,295 @@
+// Multiplayer server. Owns every board and tells each client what its
+// player can see, over the line protocol in `minesweeper::net`.
+//
+//     cargo run --bin server -- --mode coop --difficulty medium
+//     cargo run -- --connect 127.0.0.1:7878 --name alice
+//
+// In co-op everyone plays one shared board. In a race everyone gets their
+// own board from the same seed, and the first to clear theirs wins.
+use minesweeper::board::{Board, GameEvent, EASY, HARD, MEDIUM};
+use minesweeper::net::{self, ClientMessage, NetMode, ServerMessage, DEFAULT_PORT};
+use minesweeper::player::Move;
+use minesweeper::variant::Variant;
+use rand::prelude::*;
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// how many sends a client can fall behind by, and how long a write to it
+// may stall, before it is cut off
+const BACKLOG: usize = 256;
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Connection {
+    name: String,
+    // kept to cut the client off, everything sent goes through `outbox`
+    stream: TcpStream,
+    outbox: SyncSender<String>,
+    // race mode only, co-op players share `Server::shared`
+    board: Option<Board>,
+}
+
+struct Server {
+    mode: NetMode,
+    difficulty: (usize, usize),
+    seed: u64,
+    shared: Board,
+    connections: Vec<Option<Connection>>,
+    winner: Option<usize>,
+}
+
+impl Server {
+    fn board(&self) -> Board {
+        let mut board = Board::from_seed(self.difficulty, Variant::default(), self.seed);
+        board.calculate_rust_count();
+        board
+    }
+
+    fn send(&mut self, player: usize, messages: &[ServerMessage]) {
+        if let Some(Some(connection)) = self.connections.get_mut(player) {
+            let lines: String = messages
+                .iter()
+                .map(|message| message.to_line() + "\n")
+                .collect();
+            if connection.outbox.try_send(lines).is_err() {
+                // gone or too far behind; shutting it down ends its reader,
+                // which then cleans up after it
+                let _ = connection.stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+
+    fn broadcast(&mut self, messages: &[ServerMessage]) {
+        for player in 0..self.connections.len() {
+            self.send(player, messages);
+        }
+    }
+
+    fn join(&mut self, stream: TcpStream, outbox: SyncSender<String>) -> usize {
+        let player = self
+            .connections
+            .iter()
+            .position(Option::is_none)
+            .unwrap_or_else(|| {
+                self.connections.push(None);
+                self.connections.len() - 1
+            });
+        let board = match self.mode {
+            NetMode::Coop => None,
+            NetMode::Race => Some(self.board()),
+        };
+        self.connections[player] = Some(Connection {
+            name: format!("player{}", player + 1),
+            stream,
+            outbox,
+            board,
+        });
+        let mut hello = vec![ServerMessage::Welcome {
+            player,
+            mode: self.mode,
+        }];
+        hello.extend(match self.mode {
+            NetMode::Coop => net::snapshot(&self.shared, player),
+            NetMode::Race => net::snapshot(&self.board(), player),
+        });
+        if let Some(winner) = self.winner {
+            hello.push(ServerMessage::Winner { player: winner });
+        }
+        self.send(player, &hello);
+        player
+    }
+
+    fn handle(&mut self, player: usize, message: ClientMessage) {
+        match message {
+            ClientMessage::Hello { name } => {
+                if let Some(Some(connection)) = self.connections.get_mut(player) {
+                    connection.name = name.clone();
+                }
+                println!("player {} is {}", player + 1, name);
+                self.broadcast(&[ServerMessage::Joined { player, name }]);
+            }
+            ClientMessage::NewGame => self.new_game(),
+            ClientMessage::Move(mv) => self.play(player, mv),
+        }
+    }
+
+    fn new_game(&mut self) {
+        self.seed = thread_rng().gen();
+        self.winner = None;
+        match self.mode {
+            NetMode::Coop => {
+                self.shared = self.board();
+                let snapshot = net::snapshot(&self.shared, 0);
+                self.broadcast(&snapshot);
+            }
+            NetMode::Race => {
+                for player in 0..self.connections.len() {
+                    let board = self.board();
+                    let snapshot = net::snapshot(&board, player);
+                    if let Some(Some(connection)) = self.connections.get_mut(player) {
+                        connection.board = Some(board);
+                    }
+                    self.send(player, &snapshot);
+                }
+            }
+        }
+    }
+
+    fn play(&mut self, player: usize, mv: Move) {
+        let mode = self.mode;
+        let board = match mode {
+            NetMode::Coop => &mut self.shared,
+            NetMode::Race => match self.connections.get_mut(player) {
+                Some(Some(Connection {
+                    board: Some(board), ..
+                })) => board,
+                _ => return,
+            },
+        };
+        let (x, y) = mv.position();
+        if x >= board.width() || y >= board.height() {
+            self.send(
+                player,
+                &[ServerMessage::Error {
+                    message: format!("no cell at {} {}", x, y),
+                }],
+            );
+            return;
+        }
+        let before = board.clone();
+        // the first reveal is made safe in place, so a race board keeps
+        // the seed everyone else is playing
+        if let (0, Move::Reveal { .. }) = (board.revealed_safe(), mv) {
+            board.clear_rusts_at(x, y);
+        }
+        mv.apply(board);
+        let changes = net::changes(&before, board, player);
+        let progress = ServerMessage::Progress {
+            player,
+            revealed: board.revealed_safe(),
+            safe: board.safe_cells(),
+        };
+        let solved = board.outcome == Some(GameEvent::Solved);
+        match mode {
+            NetMode::Coop => self.broadcast(&changes),
+            NetMode::Race => {
+                self.send(player, &changes);
+                self.broadcast(&[progress]);
+                if solved && self.winner.is_none() {
+                    self.winner = Some(player);
+                    self.broadcast(&[ServerMessage::Winner { player }]);
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, player: usize) {
+        if let Some(connection) = self.connections[player].take() {
+            println!("{} left", connection.name);
+        }
+        self.broadcast(&[ServerMessage::Left { player }]);
+    }
+}
+
+// writes a client's lines on a thread of its own, so nothing is ever
+// written while the server is locked and a slow client only holds up itself
+fn spawn_writer(mut stream: TcpStream) -> SyncSender<String> {
+    let (outbox, queue) = mpsc::sync_channel::<String>(BACKLOG);
+    thread::spawn(move || {
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+        for lines in queue {
+            if stream.write_all(lines.as_bytes()).is_err() {
+                break;
+            }
+        }
+        let _ = stream.shutdown(Shutdown::Both);
+    });
+    outbox
+}
+
+fn serve(server: Arc<Mutex<Server>>, stream: TcpStream) {
+    let (reader, outbox) = match (stream.try_clone(), stream.try_clone()) {
+        (Ok(reader), Ok(writer)) => (BufReader::new(reader), spawn_writer(writer)),
+        _ => return,
+    };
+    let player = server.lock().unwrap().join(stream, outbox);
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let mut server = server.lock().unwrap();
+        match ClientMessage::from_line(&line) {
+            Some(message) => server.handle(player, message),
+            None => server.send(
+                player,
+                &[ServerMessage::Error {
+                    message: format!("can't read '{}'", line),
+                }],
+            ),
+        }
+    }
+    server.lock().unwrap().leave(player);
+}
+
+fn main() {
+    let mut port = DEFAULT_PORT;
+    let mut mode = NetMode::Coop;
+    let mut difficulty = EASY;
+    let mut seed = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_default();
+        match (arg.as_str(), value.as_str()) {
+            ("--port", v) if v.parse::<u16>().is_ok() => port = v.parse().unwrap(),
+            ("--seed", v) if v.parse::<u64>().is_ok() => seed = v.parse().ok(),
+            ("--mode", v) if NetMode::parse(v).is_some() => mode = NetMode::parse(v).unwrap(),
+            ("--difficulty", "easy") => difficulty = EASY,
+            ("--difficulty", "medium") => difficulty = MEDIUM,
+            ("--difficulty", "hard") => difficulty = HARD,
+            _ => {
+                eprintln!(
+                    "usage: server [--port P] [--mode coop|race] \
+                     [--difficulty easy|medium|hard] [--seed S]"
+                );
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Could not listen on port {}: {}", port, e);
+            std::process::exit(1);
+        }
+    };
+    let mut server = Server {
+        mode,
+        difficulty,
+        seed: seed.unwrap_or_else(|| thread_rng().gen()),
+        shared: Board::new(difficulty, Variant::default()),
+        connections: vec![],
+        winner: None,
+    };
+    server.shared = server.board();
+    // the port actually taken, which `--port 0` leaves to the system
+    let addr = listener
+        .local_addr()
+        .map_or_else(|_| format!("127.0.0.1:{}", port), |addr| addr.to_string());
+    println!("{} server on {}", mode.as_str(), addr);
+    let server = Arc::new(Mutex::new(server));
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let server = Arc::clone(&server);
+                thread::spawn(move || serve(server, stream));
+            }
+            Err(e) => println!("Connection failed: {}", e),
+        }
+    }
+}
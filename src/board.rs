@@ -1,138 +1,668 @@
-use ggez::graphics::Point2;
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
 use std::collections::VecDeque;
+use std::ops::Deref;
 
 use crate::cell::Cell;
+use crate::text;
+use crate::variant::Variant;
 use std::fmt;
+
+// (rusts, cells per row) for the standard difficulties
+pub const EASY: (usize, usize) = (10, 9);
+pub const MEDIUM: (usize, usize) = (37, 18);
+pub const HARD: (usize, usize) = (82, 27);
+
+/// How a move ended the game. Moves report this the moment it happens, so
+/// the UI never has to scan the board to find out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    /// Every safe cell is uncovered.
+    Solved,
+    /// The rust at (x, y) was uncovered.
+    Failed { x: usize, y: usize },
+}
+
+/// A board's cells in one flat vec, column by column, so (x, y) is at
+/// `x * height + y`.
 #[derive(Debug, Clone)]
 pub struct Board {
-    pub cells: Vec<Vec<Cell>>,
+    cells: Vec<Cell>,
+    width: usize,
+    height: usize,
     pub difficulty: (usize, usize),
+    pub variant: Variant,
+    pub outcome: Option<GameEvent>,
+    // kept up to date as cells change, see `uncover` and `cycle_flag`
+    safe_cells: usize,
+    revealed_safe: usize,
+    flags_placed: usize,
+}
+
+// the neighbors of one cell without allocating, for the loops over whole
+// boards. Radius 2 has the most, 24
+struct Around {
+    cells: [(usize, usize); 24],
+    len: usize,
+}
+
+impl Deref for Around {
+    type Target = [(usize, usize)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.cells[..self.len]
+    }
 }
 
+// the plain text format from `text`, so any board can be printed and read
+// back in
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let display = self
-            .cells
-            .iter()
-            .map(|o| {
-                o.iter()
-                    .map(|i| format!("{}", i.rust_count))
-                    .collect::<Vec<String>>()
-                    .join(" ")
-            })
-            .collect::<Vec<String>>()
-            .join("\n");
-        write!(f, "{}", display)
+        write!(f, "{}", text::write(self))
     }
 }
 
 impl Board {
-    pub fn new(difficulty: (usize, usize)) -> Self {
-        let mut rng = rand::thread_rng();
-        let mut cells = vec![];
-        let (_, cells_row) = difficulty;
-
-        let mut starting_states: Vec<bool> = (0..=(cells_row.pow(2))).map(|n| n % 9 == 0).collect();
-
-        starting_states.shuffle(&mut rng);
-        for i in 0..cells_row {
-            let mut inner = vec![];
-            for j in 0..cells_row {
-                let cell = Cell::new(
-                    Point2::new(i as f32, j as f32),
-                    starting_states[i * cells_row + j],
-                );
-                inner.push(cell);
+    pub fn new(difficulty: (usize, usize), variant: Variant) -> Self {
+        Board::generate(difficulty, variant, &mut rand::thread_rng())
+    }
+
+    // the same seed always gives the same board. ChaCha8 rather than
+    // `StdRng`, whose algorithm rand may change, so daily boards and seeds
+    // shared between players stay put across updates
+    pub fn from_seed(difficulty: (usize, usize), variant: Variant, seed: u64) -> Self {
+        Board::generate(difficulty, variant, &mut ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    pub fn generate<R: Rng>(difficulty: (usize, usize), variant: Variant, rng: &mut R) -> Self {
+        let (rusts, cells_row) = difficulty;
+
+        // every cell gets one slot per rust it may hold, and the rusts are
+        // dropped into a random pick of those slots
+        let mut slots: Vec<usize> = (0..cells_row.pow(2))
+            .flat_map(|n| std::iter::repeat_n(n, variant.max_rusts as usize))
+            .collect();
+        slots.shuffle(rng);
+        let mut starting_states = vec![0; cells_row.pow(2)];
+        for &n in slots.iter().take(rusts) {
+            starting_states[n] += 1;
+        }
+
+        let safe_cells = starting_states.iter().filter(|&&n| n == 0).count();
+        Board {
+            cells: starting_states.into_iter().map(Cell::new).collect(),
+            width: cells_row,
+            height: cells_row,
+            difficulty,
+            variant,
+            outcome: None,
+            safe_cells,
+            revealed_safe: 0,
+            flags_placed: 0,
+        }
+    }
+
+    // a board with cells laid out by hand, in any state, column by column.
+    // Counts, the counters and the outcome are worked out from the cells;
+    // the difficulty becomes (rusts, columns)
+    pub fn from_cells(width: usize, height: usize, cells: Vec<Cell>, variant: Variant) -> Self {
+        assert_eq!(
+            cells.len(),
+            width * height,
+            "not a {}x{} board",
+            width,
+            height
+        );
+        let mut board = Board {
+            cells,
+            width,
+            height,
+            difficulty: (0, 0),
+            variant,
+            outcome: None,
+            safe_cells: 0,
+            revealed_safe: 0,
+            flags_placed: 0,
+        };
+        board.difficulty = (board.total_rusts(), width);
+        board.calculate_rust_count();
+        for cell in &board.cells {
+            board.flags_placed += cell.flags() as usize;
+            if !cell.is_rust() {
+                board.safe_cells += 1;
+                if !cell.is_hidden() {
+                    board.revealed_safe += 1;
+                }
+            }
+        }
+        let fatal = board
+            .cells
+            .iter()
+            .position(|c| c.is_rust() && !c.is_hidden());
+        if let Some(n) = fatal {
+            let (x, y) = board.position(n);
+            board.outcome = Some(GameEvent::Failed { x, y });
+        } else if board.safe_cells > 0 && board.revealed_safe == board.safe_cells {
+            board.outcome = Some(GameEvent::Solved);
+        }
+        board
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    // generated boards are square but loaded ones need not be
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Cell {
+        self.cells[x * self.height + y]
+    }
+
+    // for mirroring a board cell by cell, as clients of a server do. The
+    // counters and the outcome don't follow changes made through this
+    pub fn cell_mut(&mut self, x: usize, y: usize) -> &mut Cell {
+        &mut self.cells[x * self.height + y]
+    }
+
+    // every cell, column by column
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    // where the nth of `cells` sits
+    pub fn position(&self, n: usize) -> (usize, usize) {
+        (n / self.height, n % self.height)
+    }
+
+    // every (x, y) on the board, in the same order as `cells`
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let height = self.height;
+        (0..self.width).flat_map(move |x| (0..height).map(move |y| (x, y)))
+    }
+
+    // coordinates of every cell touching (x, y) under the variant's
+    // neighborhood. On a wrapping board the edges connect, otherwise
+    // neighbors falling off the board are skipped
+    pub fn neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        self.around(x, y).to_vec()
+    }
+
+    fn around(&self, x: usize, y: usize) -> Around {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let mut around = Around {
+            cells: [(0, 0); 24],
+            len: 0,
+        };
+        for &(i, j) in self.variant.neighborhood.offsets() {
+            let (mut nx, mut ny) = (x as i32 + i, y as i32 + j);
+            if self.variant.wrapping {
+                nx = nx.rem_euclid(width);
+                ny = ny.rem_euclid(height);
+            } else if nx < 0 || nx >= width || ny < 0 || ny >= height {
+                continue;
             }
-            cells.push(inner);
+            let neighbor = (nx as usize, ny as usize);
+            // tiny wrapping boards reach the same cell from several sides
+            if self.variant.wrapping && (neighbor == (x, y) || around.contains(&neighbor)) {
+                continue;
+            }
+            around.cells[around.len] = neighbor;
+            around.len += 1;
         }
-        Board { cells, difficulty }
+        around
     }
 
+    // neighborhoods are symmetric, so rather than every cell looking for
+    // rusts around it, each rust adds itself to the cells it touches
     pub fn calculate_rust_count(&mut self) {
-        let (_, cells) = self.difficulty;
-        for i in 0..cells {
-            for j in 0..cells {
-                let mut rusts_found = 0;
-                'k: for k in -1..=1 {
-                    'l: for l in -1..=1 {
-                        if (i == 0 && k == -1) || (i == cells - 1 && k == 1) {
-                            continue 'k;
-                        }
-                        if (j == 0 && l == -1) || (j == cells - 1 && l == 1) {
-                            continue 'l;
-                        }
-                        if k != 0 || l != 0 {
-                            if self.cells[(i as i32 + k) as usize][(j as i32 + l) as usize].is_rust
-                            {
-                                rusts_found += 1;
-                            }
-                        }
-                    }
+        for cell in &mut self.cells {
+            cell.set_rust_count(0);
+        }
+        for n in 0..self.cells.len() {
+            let rusts = self.cells[n].rusts();
+            if rusts == 0 {
+                continue;
+            }
+            let (x, y) = self.position(n);
+            for &(nx, ny) in self.around(x, y).iter() {
+                let cell = self.cell_mut(nx, ny);
+                cell.set_rust_count(cell.rust_count() + rusts);
+            }
+        }
+    }
+
+    // moves the rusts out of (x, y) into the first cells with room, scanning
+    // from the top left, so a first click is safe without rolling a new
+    // board. Seeded boards stay the same for everyone opening on the same
+    // cell. Only meant for a board nothing has been uncovered on yet
+    pub fn clear_rusts_at(&mut self, x: usize, y: usize) {
+        let mut moving = self.cell(x, y).rusts();
+        if moving == 0 {
+            return;
+        }
+        self.cell_mut(x, y).set_rusts(0);
+        let max_rusts = self.variant.max_rusts;
+        for j in 0..self.height {
+            for i in 0..self.width {
+                if moving == 0 {
+                    break;
                 }
-                self.cells[i][j].rust_count = rusts_found;
+                if (i, j) == (x, y) {
+                    continue;
+                }
+                let cell = self.cell_mut(i, j);
+                let taken = (max_rusts - cell.rusts()).min(moving);
+                cell.set_rusts(cell.rusts() + taken);
+                moving -= taken;
             }
         }
+        // a board without room elsewhere keeps the leftovers where they were
+        self.cell_mut(x, y).set_rusts(moving);
+        self.safe_cells = self.cells.iter().filter(|c| !c.is_rust()).count();
+        self.calculate_rust_count();
     }
 
-    // if ignore_rules is true (in the event a bomb was clicked on), flood fill
-    // actually performs a flood fill. Otherwise this implements standard
-    // minesweeper rules
-    pub fn flood_fill(&mut self, cell_x: usize, cell_y: usize) {
-        let mut queue: VecDeque<Cell> = VecDeque::new();
-        queue.push_front(self.cells[cell_x][cell_y].clone());
-
-        while let Some(cell) = queue.pop_front() {
-            if !cell.is_rust && !cell.is_flagged {
-                let (x, y) = (cell.position.x as usize, cell.position.y as usize);
-                self.cells[x][y].is_hidden = false;
-                'outer: for i in -1..=1 {
-                    for j in -1..=1 {
-                        if cell.position.x == 0.0 && i == -1
-                            || cell.position.x == self.difficulty.1 as f32 - 1.0 && i == 1
-                        {
-                            continue 'outer;
-                        }
-                        if cell.position.y == 0.0 && j == -1
-                            || cell.position.y == self.difficulty.1 as f32 - 1.0 && j == 1
-                        {
-                            continue;
-                        }
-                        if i != 0 || j != 0 {
-                            if !self.cells[(cell.position.x as i8 + i) as usize]
-                                [(cell.position.y as i8 + j) as usize]
-                                .is_flagged
-                                && self.cells[(cell.position.x as i8 + i) as usize]
-                                    [(cell.position.y as i8 + j) as usize]
-                                    .rust_count
-                                    == 0
-                                && self.cells[(cell.position.x as i8 + i) as usize]
-                                    [(cell.position.y as i8 + j) as usize]
-                                    .is_hidden
-                            {
-                                let new_cell = self.cells[(cell.position.x as i8 + i) as usize]
-                                    [(cell.position.y as i8 + j) as usize]
-                                    .clone();
-                                queue.push_back(new_cell);
-                            } else if !self.cells[(cell.position.x as i8 + i) as usize]
-                                [(cell.position.y as i8 + j) as usize]
-                                .is_flagged
-                                && !self.cells[(cell.position.x as i8 + i) as usize]
-                                    [(cell.position.y as i8 + j) as usize]
-                                    .is_rust
-                            {
-                                self.cells[(cell.position.x as i8 + i) as usize]
-                                    [(cell.position.y as i8 + j) as usize]
-                                    .is_hidden = false;
-                            }
+    // uncovers a single cell, opening up the surrounding area when it has
+    // no rusts around it. Flagged cells are left alone. Returns the event
+    // if this move ended the game
+    pub fn reveal(&mut self, x: usize, y: usize) -> Option<GameEvent> {
+        if self.outcome.is_some() {
+            return None;
+        }
+        let cell = self.cell(x, y);
+        if cell.is_flagged() {
+            return None;
+        }
+        // if count is 0, trigger flood fill following rules
+        if cell.rust_count() == 0 && !cell.is_rust() {
+            self.flood_fill(x, y);
+        } else {
+            self.uncover(x, y);
+        }
+        self.outcome
+    }
+
+    // right clicking a hidden cell adds a flag, wrapping back to none once
+    // it holds as many flags as a cell can hold rusts
+    pub fn cycle_flag(&mut self, x: usize, y: usize) {
+        if self.outcome.is_some() {
+            return;
+        }
+        let max_rusts = self.variant.max_rusts;
+        let cell = self.cell(x, y);
+        if cell.is_hidden() {
+            self.set_flags(x, y, (cell.flags() + 1) % (max_rusts + 1));
+        }
+    }
+
+    // puts exactly `flags` flags on a cell, keeping the count of flags
+    // placed. For boards that mirror one played elsewhere
+    pub fn set_flags(&mut self, x: usize, y: usize, flags: u8) {
+        let cell = self.cell(x, y);
+        self.flags_placed = self.flags_placed - cell.flags() as usize + flags as usize;
+        self.cell_mut(x, y).set_flags(flags);
+    }
+
+    // `cycle_flag` with a question mark after the last flag, for cells the
+    // player is unsure about. A question mark is only a note: it doesn't
+    // stop a reveal and doesn't count towards a chord
+    pub fn cycle_mark(&mut self, x: usize, y: usize) {
+        let cell = self.cell(x, y);
+        if self.outcome.is_some() || !cell.is_hidden() {
+            return;
+        }
+        if cell.is_marked() {
+            self.cell_mut(x, y).set_marked(false);
+            return;
+        }
+        self.cycle_flag(x, y);
+        if cell.flags() == self.variant.max_rusts {
+            self.cell_mut(x, y).set_marked(true);
+        }
+    }
+
+    // the one place a cell gets uncovered, so the counters and the outcome
+    // always follow the cells
+    fn uncover(&mut self, x: usize, y: usize) {
+        let cell = self.cell_mut(x, y);
+        if !cell.is_hidden() {
+            return;
+        }
+        cell.set_hidden(false);
+        cell.set_marked(false);
+        if cell.is_rust() {
+            if self.outcome.is_none() {
+                self.outcome = Some(GameEvent::Failed { x, y });
+            }
+        } else {
+            self.revealed_safe += 1;
+            if self.revealed_safe == self.safe_cells && self.outcome.is_none() {
+                self.outcome = Some(GameEvent::Solved);
+            }
+        }
+    }
+
+    // number of cells without any rust, all of which must be uncovered to win
+    pub fn safe_cells(&self) -> usize {
+        self.safe_cells
+    }
+
+    pub fn revealed_safe(&self) -> usize {
+        self.revealed_safe
+    }
+
+    // total flags on the board, stacked flags counted one by one
+    pub fn flags_placed(&self) -> usize {
+        self.flags_placed
+    }
+
+    // rusts actually hidden in the board, which can fall short of the
+    // difficulty when the board has no room for them all
+    pub fn total_rusts(&self) -> usize {
+        self.cells.iter().map(|cell| cell.rusts() as usize).sum()
+    }
+
+    // reveals every unflagged neighbor of an uncovered number, as long as
+    // the player has placed as many flags around it as the number says
+    pub fn chord(&mut self, x: usize, y: usize) -> Option<GameEvent> {
+        if self.outcome.is_some() {
+            return None;
+        }
+        let cell = self.cell(x, y);
+        if cell.is_hidden() || cell.is_rust() {
+            return None;
+        }
+        let around = self.around(x, y);
+        let flags: u8 = around
+            .iter()
+            .map(|&(nx, ny)| self.cell(nx, ny).flags())
+            .sum();
+        if flags != cell.rust_count() {
+            return None;
+        }
+        for &(nx, ny) in around.iter() {
+            if self.cell(nx, ny).is_hidden() {
+                self.reveal(nx, ny);
+            }
+        }
+        self.outcome
+    }
+
+    // Bechtel's Board Benchmark Value: the fewest clicks that clear the
+    // board. Each opening (connected patch of zeros) takes one click, and
+    // so does every other safe cell no opening uncovers
+    pub fn three_bv(&self) -> usize {
+        let is_zero = |x: usize, y: usize| {
+            let cell = self.cell(x, y);
+            !cell.is_rust() && cell.rust_count() == 0
+        };
+        let height = self.height;
+        let mut opened = vec![false; self.cells.len()];
+        let mut clicks = 0;
+        for (x, y) in self.positions() {
+            if opened[x * height + y] || !is_zero(x, y) {
+                continue;
+            }
+            clicks += 1;
+            opened[x * height + y] = true;
+            let mut stack = vec![(x, y)];
+            while let Some((cx, cy)) = stack.pop() {
+                for &(nx, ny) in self.around(cx, cy).iter() {
+                    if !opened[nx * height + ny] {
+                        opened[nx * height + ny] = true;
+                        if is_zero(nx, ny) {
+                            stack.push((nx, ny));
                         }
                     }
                 }
-            } else if cell.is_rust {
-                self.cells[cell_x][cell_y].game_over = true;
-                self.cells[cell_x][cell_y].is_hidden = false;
             }
         }
+        clicks
+            + self
+                .cells
+                .iter()
+                .zip(&opened)
+                .filter(|&(cell, &opened)| !cell.is_rust() && !opened)
+                .count()
+    }
+
+    // uncovers the patch of zeros connected to (x, y) along with every cell
+    // bordering it, the way an empty area opens up. Flagged cells are left
+    // alone and stop the spread. Each cell is queued at most once, so this
+    // is linear in the size of the area however big the board. Starting on
+    // a rust just uncovers it, ending the game
+    pub fn flood_fill(&mut self, x: usize, y: usize) {
+        let start = self.cell(x, y);
+        if !start.is_hidden() || start.is_flagged() {
+            return;
+        }
+        if start.is_rust() {
+            self.uncover(x, y);
+            return;
+        }
+        // cells are uncovered as they are queued, so being uncovered is what
+        // keeps them from being queued twice
+        let mut queue = VecDeque::new();
+        self.uncover(x, y);
+        queue.push_back((x, y));
+        while let Some((x, y)) = queue.pop_front() {
+            // numbers are the border, only zeros spread
+            if self.cell(x, y).rust_count() != 0 {
+                continue;
+            }
+            for &(nx, ny) in self.around(x, y).iter() {
+                let neighbor = self.cell(nx, ny);
+                if !neighbor.is_hidden() || neighbor.is_flagged() || neighbor.is_rust() {
+                    continue;
+                }
+                self.uncover(nx, ny);
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(layout: &str) -> Board {
+        text::parse(layout).unwrap()
+    }
+
+    // counts row by row, the way the layout is written
+    fn counts(board: &Board) -> Vec<Vec<u8>> {
+        (0..board.height())
+            .map(|y| {
+                (0..board.width())
+                    .map(|x| board.cell(x, y).rust_count())
+                    .collect()
+            })
+            .collect()
+    }
+
+    // '.' for uncovered, '#' for hidden and 'F' for flagged, row by row
+    fn state(board: &Board) -> Vec<String> {
+        (0..board.height())
+            .map(|y| {
+                (0..board.width())
+                    .map(|x| match board.cell(x, y) {
+                        cell if !cell.is_hidden() => '.',
+                        cell if cell.is_flagged() => 'F',
+                        _ => '#',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn counts_at_corners_and_edges() {
+        let b = board("*..\n...\n..*\n");
+        assert_eq!(
+            counts(&b),
+            vec![vec![0, 1, 0], vec![1, 2, 1], vec![0, 1, 0]]
+        );
+    }
+
+    #[test]
+    fn counts_wrap_around_the_edges() {
+        let b = board("variant wrap\n*..\n...\n...\n");
+        // every other cell of a 3x3 wrapping board touches the corner
+        assert_eq!(
+            counts(&b),
+            vec![vec![0, 1, 1], vec![1, 1, 1], vec![1, 1, 1]]
+        );
+    }
+
+    #[test]
+    fn counts_add_up_stacked_rusts() {
+        let b = board("variant rusts=3\n3.\n.2\n");
+        assert_eq!(counts(&b), vec![vec![2, 5], vec![5, 3]]);
+    }
+
+    #[test]
+    fn single_row() {
+        let mut b = board("*.*..\n");
+        assert_eq!(counts(&b), vec![vec![0, 2, 0, 1, 0]]);
+        assert_eq!(b.reveal(4, 0), None);
+        assert_eq!(state(&b), vec!["###.."]);
+        assert_eq!(b.reveal(1, 0), Some(GameEvent::Solved));
+    }
+
+    #[test]
+    fn single_column() {
+        let mut b = board("*\n.\n.\n");
+        assert_eq!(counts(&b), vec![vec![0], vec![1], vec![0]]);
+        assert_eq!(b.reveal(0, 2), Some(GameEvent::Solved));
+        assert_eq!(state(&b), vec!["#", ".", "."]);
+    }
+
+    #[test]
+    fn single_wrapping_row() {
+        let mut b = board("variant wrap\n*...\n");
+        assert_eq!(counts(&b), vec![vec![0, 1, 0, 1]]);
+        assert_eq!(b.reveal(2, 0), Some(GameEvent::Solved));
+    }
+
+    #[test]
+    fn single_cell() {
+        let mut b = board(".\n");
+        assert_eq!(b.neighbors(0, 0), vec![]);
+        assert_eq!(b.reveal(0, 0), Some(GameEvent::Solved));
+        let mut b = board("variant wrap\n*\n");
+        assert_eq!(b.neighbors(0, 0), vec![]);
+        assert_eq!(b.reveal(0, 0), Some(GameEvent::Failed { x: 0, y: 0 }));
+    }
+
+    #[test]
+    fn flood_fill_stops_at_numbers() {
+        let mut b = board(".*...\n.*...\n.*...\n");
+        b.reveal(4, 0);
+        assert_eq!(state(&b), vec!["##...", "##...", "##..."]);
+        assert_eq!(b.outcome, None);
+    }
+
+    #[test]
+    fn flood_fill_opens_huge_boards() {
+        let mut b = Board::from_seed((0, 1000), Variant::default(), 1);
+        b.calculate_rust_count();
+        assert_eq!(b.reveal(999, 0), Some(GameEvent::Solved));
+        assert_eq!(b.revealed_safe(), 1_000_000);
+    }
+
+    #[test]
+    fn flood_fill_leaves_flags_alone() {
+        let mut b = board("....\n....\n...*\n\n#F##\n####\n####\n");
+        b.reveal(0, 2);
+        assert_eq!(state(&b), vec![".F..", "....", "...#"]);
+        assert!(b.cell(1, 0).is_hidden());
+        // the flagged cell is safe, so the game goes on
+        assert_eq!(b.outcome, None);
+        assert_eq!(b.revealed_safe(), b.safe_cells() - 1);
+    }
+
+    #[test]
+    fn flags_cannot_be_revealed() {
+        let mut b = board("*.\n..\n\nF#\n##\n");
+        assert_eq!(b.reveal(0, 0), None);
+        assert!(b.cell(0, 0).is_hidden());
+    }
+
+    #[test]
+    fn loss_ends_the_game() {
+        let mut b = board("*.\n..\n");
+        assert_eq!(b.reveal(0, 0), Some(GameEvent::Failed { x: 0, y: 0 }));
+        assert_eq!(b.outcome, Some(GameEvent::Failed { x: 0, y: 0 }));
+        // nothing moves once the game is over
+        assert_eq!(b.reveal(1, 1), None);
+        b.cycle_flag(1, 0);
+        assert!(b.cell(1, 1).is_hidden());
+        assert_eq!(b.flags_placed(), 0);
+    }
+
+    #[test]
+    fn win_comes_with_the_last_safe_cell() {
+        let mut b = board("*..\n");
+        assert_eq!(b.reveal(1, 0), None);
+        assert_eq!(b.reveal(1, 0), None);
+        assert_eq!(b.reveal(2, 0), Some(GameEvent::Solved));
+        assert_eq!(b.outcome, Some(GameEvent::Solved));
+    }
+
+    #[test]
+    fn loaded_boards_know_their_outcome() {
+        assert_eq!(board("*.\n\n#.\n").outcome, Some(GameEvent::Solved));
+        let lost = board("*.\n\n..\n");
+        assert_eq!(lost.outcome, Some(GameEvent::Failed { x: 0, y: 0 }));
+        assert_eq!(board("*.\n").outcome, None);
+    }
+
+    #[test]
+    fn chord_needs_the_right_number_of_flags() {
+        let mut b = board(".*.\n...\n\n.##\n###\n");
+        assert_eq!(b.chord(0, 0), None);
+        assert_eq!(state(&b), vec![".##", "###"]);
+        b.cycle_flag(1, 0);
+        assert_eq!(b.chord(0, 0), None);
+        assert_eq!(state(&b), vec![".F#", "..#"]);
+    }
+
+    #[test]
+    fn chord_around_a_wrong_flag_loses() {
+        let mut b = board(".*.\n...\n\n.##\n###\n");
+        b.cycle_flag(0, 1);
+        assert_eq!(b.chord(0, 0), Some(GameEvent::Failed { x: 1, y: 0 }));
+    }
+
+    #[test]
+    fn clearing_rusts_keeps_them_on_the_board() {
+        let mut b = board("**.\n...\n");
+        b.clear_rusts_at(0, 0);
+        assert_eq!(b.cell(0, 0).rusts(), 0);
+        assert_eq!(b.cell(2, 0).rusts(), 1);
+        assert_eq!(b.total_rusts(), 2);
+        assert_eq!(b.safe_cells(), 4);
+        assert_eq!(counts(&b), vec![vec![1, 1, 1], vec![1, 2, 2]]);
+        // with nowhere else to go they stay put
+        let mut full = board("**\n**\n");
+        full.clear_rusts_at(0, 0);
+        assert_eq!(full.cell(0, 0).rusts(), 1);
+    }
+
+    #[test]
+    fn generation_places_every_rust() {
+        for &difficulty in &[EASY, MEDIUM, HARD] {
+            let b = Board::from_seed(difficulty, Variant::default(), 1);
+            assert_eq!(b.total_rusts(), difficulty.0);
+            assert_eq!((b.width(), b.height()), (difficulty.1, difficulty.1));
+            assert_eq!(b.safe_cells(), difficulty.1.pow(2) - difficulty.0);
+        }
+        // more rusts than fit fill the board and no more
+        let crowded = Board::from_seed((20, 3), Variant::default(), 1);
+        assert_eq!(crowded.total_rusts(), 9);
     }
 }
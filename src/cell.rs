@@ -1,25 +1,98 @@
-use ggez::graphics::Point2;
-#[derive(Clone, Debug)]
-pub struct Cell {
-    pub position: Point2,
-    pub rust_count: u8,
-    pub is_rust: bool,
-    pub is_hidden: bool,
-    pub game_over: bool,
-    pub is_flagged: bool,
-    pub scaling: f32,
-}
+use std::fmt;
+
+// bits of the packed cell, low to high
+const COUNT_BITS: u32 = 0xff;
+const RUSTS_SHIFT: u32 = 8;
+const FLAGS_SHIFT: u32 = 12;
+const NIBBLE: u32 = 0xf;
+const HIDDEN: u32 = 1 << 16;
+const MARKED: u32 = 1 << 17;
+
+/// One cell's game state packed into four bytes: the rusts touching it in
+/// the low byte (up to 9 rusts on each of 24 neighbors still fits), then
+/// the rusts and the flags on it a nibble each, then whether it is still
+/// covered and whether the player put a question mark on it. Where the cell
+/// sits is its index in the board, and how it looks on screen is worked out
+/// by `frame`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Cell(u32);
 
 impl Cell {
-    pub fn new(position: Point2, is_rust: bool) -> Self {
-        Cell {
-            position,
-            rust_count: 0,
-            is_rust,
-            is_hidden: true, //TODO: change this back to true
-            game_over: false,
-            is_flagged: false,
-            scaling: 0.0,
+    // a covered cell with no flags, counted later by the board
+    pub fn new(rusts: u8) -> Self {
+        let mut cell = Cell(HIDDEN);
+        cell.set_rusts(rusts);
+        cell
+    }
+
+    pub fn rust_count(self) -> u8 {
+        (self.0 & COUNT_BITS) as u8
+    }
+
+    // more than one only when the variant allows stacking rusts
+    pub fn rusts(self) -> u8 {
+        ((self.0 >> RUSTS_SHIFT) & NIBBLE) as u8
+    }
+
+    pub fn flags(self) -> u8 {
+        ((self.0 >> FLAGS_SHIFT) & NIBBLE) as u8
+    }
+
+    pub fn is_hidden(self) -> bool {
+        self.0 & HIDDEN != 0
+    }
+
+    pub fn is_rust(self) -> bool {
+        self.rusts() > 0
+    }
+
+    pub fn is_flagged(self) -> bool {
+        self.flags() > 0
+    }
+
+    pub fn is_marked(self) -> bool {
+        self.0 & MARKED != 0
+    }
+
+    pub fn set_rust_count(&mut self, count: u8) {
+        self.0 = (self.0 & !COUNT_BITS) | count as u32;
+    }
+
+    pub fn set_rusts(&mut self, rusts: u8) {
+        debug_assert!(rusts as u32 <= NIBBLE);
+        self.0 = (self.0 & !(NIBBLE << RUSTS_SHIFT)) | ((rusts as u32 & NIBBLE) << RUSTS_SHIFT);
+    }
+
+    pub fn set_flags(&mut self, flags: u8) {
+        debug_assert!(flags as u32 <= NIBBLE);
+        self.0 = (self.0 & !(NIBBLE << FLAGS_SHIFT)) | ((flags as u32 & NIBBLE) << FLAGS_SHIFT);
+    }
+
+    pub fn set_hidden(&mut self, hidden: bool) {
+        if hidden {
+            self.0 |= HIDDEN;
+        } else {
+            self.0 &= !HIDDEN;
+        }
+    }
+
+    pub fn set_marked(&mut self, marked: bool) {
+        if marked {
+            self.0 |= MARKED;
+        } else {
+            self.0 &= !MARKED;
         }
     }
 }
+
+impl fmt::Debug for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Cell")
+            .field("rust_count", &self.rust_count())
+            .field("rusts", &self.rusts())
+            .field("flags", &self.flags())
+            .field("is_hidden", &self.is_hidden())
+            .field("is_marked", &self.is_marked())
+            .finish()
+    }
+}
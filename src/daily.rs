@@ -0,0 +1,285 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::board::{Board, MEDIUM};
+use crate::storage;
+use crate::variant::Variant;
+
+const DAILY_FILE: &str = "daily.txt";
+const SHARE_FILE: &str = "daily-share.txt";
+
+/// Every daily board is a classic board of this size.
+pub const DAILY_DIFFICULTY: (usize, usize) = MEDIUM;
+
+/// A calendar day in UTC, so players in every timezone share a board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Date::from_days((secs / 86_400) as i64)
+    }
+
+    // days since 1970-01-01 to a proleptic gregorian date, after Howard
+    // Hinnant's civil_from_days
+    pub fn from_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        Date { year, month, day }
+    }
+
+    /// Parses `YYYY-MM-DD`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(3, '-');
+        let date = Date {
+            year: parts.next()?.parse().ok()?,
+            month: parts.next()?.parse().ok()?,
+            day: parts.next()?.parse().ok()?,
+        };
+        if (1..=12).contains(&date.month) && (1..=31).contains(&date.day) {
+            Some(date)
+        } else {
+            None
+        }
+    }
+
+    // FNV-1a over the date as written, which is stable across platforms
+    // and versions unlike std's hasher
+    pub fn seed(&self) -> u64 {
+        self.to_string()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+                (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    /// The board everyone plays on this day, counts filled in.
+    pub fn board(&self) -> Board {
+        let mut board = Board::from_seed(DAILY_DIFFICULTY, Variant::default(), self.seed());
+        board.calculate_rust_count();
+        board
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// The scored attempt at one day's board.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyResult {
+    pub date: Date,
+    pub seed: u64,
+    /// `None` while the attempt is still being played, or was abandoned.
+    pub won: Option<bool>,
+    pub seconds: f64,
+    pub clicks: usize,
+}
+
+impl DailyResult {
+    /// An attempt that has just been started.
+    pub fn started(date: Date) -> Self {
+        DailyResult {
+            date,
+            seed: date.seed(),
+            won: None,
+            seconds: 0.0,
+            clicks: 0,
+        }
+    }
+
+    // same key=value layout as the stats file
+    pub fn to_line(&self) -> String {
+        let result = match self.won {
+            None => "started",
+            Some(true) => "won",
+            Some(false) => "lost",
+        };
+        format!(
+            "date={} seed={} result={} time={:.3} clicks={}",
+            self.date, self.seed, result, self.seconds, self.clicks
+        )
+    }
+
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut date = None;
+        let mut result = DailyResult {
+            date: Date::from_days(0),
+            seed: 0,
+            won: None,
+            seconds: 0.0,
+            clicks: 0,
+        };
+        for pair in line.split_whitespace() {
+            let mut parts = pair.splitn(2, '=');
+            let (key, value) = (parts.next()?, parts.next()?);
+            match key {
+                "date" => date = Some(Date::parse(value)?),
+                "seed" => result.seed = value.parse().ok()?,
+                "result" => {
+                    result.won = match value {
+                        "won" => Some(true),
+                        "lost" => Some(false),
+                        _ => None,
+                    }
+                }
+                "time" => result.seconds = value.parse().ok()?,
+                "clicks" => result.clicks = value.parse().ok()?,
+                _ => (),
+            }
+        }
+        result.date = date?;
+        Some(result)
+    }
+
+    /// A one line summary to paste to friends, spoiling nothing but the
+    /// seed they already have.
+    pub fn share_string(&self) -> String {
+        let outcome = match self.won {
+            Some(true) => format!("solved in {:.1}s", self.seconds),
+            Some(false) => format!("rusted after {:.1}s", self.seconds),
+            None => "unfinished".to_owned(),
+        };
+        format!(
+            "Rustsweeper Daily {}: {}, {} clicks (seed {})",
+            self.date, outcome, self.clicks, self.seed
+        )
+    }
+}
+
+/// Adds a line to the daily results file. An attempt is recorded once as
+/// started and again when it ends; the last line for a date wins.
+pub fn record(result: &DailyResult) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(storage::data_file(DAILY_FILE)?)?;
+    writeln!(file, "{}", result.to_line())
+}
+
+/// The latest result for every day played, oldest first.
+pub fn load() -> io::Result<Vec<DailyResult>> {
+    let contents = match fs::read_to_string(storage::data_file(DAILY_FILE)?) {
+        Ok(contents) => contents,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut results: Vec<DailyResult> = vec![];
+    for result in contents.lines().filter_map(DailyResult::from_line) {
+        match results.iter_mut().find(|r| r.date == result.date) {
+            Some(existing) => *existing = result,
+            None => results.push(result),
+        }
+    }
+    Ok(results)
+}
+
+/// Whether the scored attempt for `date` has already been used.
+pub fn attempted(date: Date) -> io::Result<bool> {
+    Ok(load()?.iter().any(|r| r.date == date))
+}
+
+/// Writes the share string next to the other game files, returning where.
+pub fn export_share(result: &DailyResult) -> io::Result<PathBuf> {
+    let path = storage::data_file(SHARE_FILE)?;
+    fs::write(&path, format!("{}\n", result.share_string()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i64, month: u32, day: u32) -> Date {
+        Date { year, month, day }
+    }
+
+    #[test]
+    fn days_since_the_epoch() {
+        assert_eq!(Date::from_days(0), date(1970, 1, 1));
+        assert_eq!(Date::from_days(-1), date(1969, 12, 31));
+        assert_eq!(Date::from_days(59), date(1970, 3, 1));
+        assert_eq!(Date::from_days(11_016), date(2000, 2, 29));
+        assert_eq!(Date::from_days(20_745), date(2026, 10, 19));
+    }
+
+    #[test]
+    fn dates_parse_as_written() {
+        for days in &[-1, 0, 11_016, 20_745] {
+            let date = Date::from_days(*days);
+            assert_eq!(Date::parse(&date.to_string()), Some(date));
+        }
+        assert_eq!(Date::parse("2024-1-5"), Some(date(2024, 1, 5)));
+        for bad in &[
+            "",
+            "2024",
+            "2024-01",
+            "2024-13-01",
+            "2024-00-10",
+            "2024-01-32",
+            "a-b-c",
+        ] {
+            assert_eq!(Date::parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn every_day_has_its_own_fixed_seed() {
+        // pinned, so an update can't quietly change everyone's board
+        assert_eq!(date(2024, 1, 1).seed(), 0x3763_7d7a_01d6_21a9);
+        assert_ne!(date(2024, 1, 1).seed(), date(2024, 1, 2).seed());
+        let day = date(2026, 10, 19);
+        assert_eq!(DailyResult::started(day).seed, day.seed());
+        let (a, b) = (day.board(), day.board());
+        assert!((0..a.width())
+            .all(|x| (0..a.height()).all(|y| a.cell(x, y).rusts() == b.cell(x, y).rusts())));
+    }
+
+    #[test]
+    fn new_years_day_2024_is_pinned() {
+        let board = date(2024, 1, 1).board();
+        assert_eq!(
+            crate::text::write(&board),
+            "\
+......*..*.*......
+...*..............
+......**..........
+.*.........*..*...
+.......*..*.......
+................*.
+*.......*.........
+....*.....*..*....
+.....*..*.........
+.....*............
+.......*..*.......
+....*...*.....*...
+*.*...............
+............*.....
+*.............*...
+....*.*...*.....**
+.............*....
+............*.....
+"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Everything the game can fail at without it being a bug. None of these
+//! are worth crashing over: the game shows them and carries on.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum Error {
+    /// An image, font or mesh the window needs couldn't be loaded or built.
+    Asset { name: String, message: String },
+    /// The settings file is there but can't be read or understood.
+    Config { path: PathBuf, message: String },
+    /// A file the game keeps between runs couldn't be read or written.
+    /// `action` says which, such as "save stats".
+    Save {
+        action: &'static str,
+        source: io::Error,
+    },
+    /// A board couldn't be set up from where it was meant to come from, a
+    /// board file or a bundled puzzle.
+    Board { from: String, message: String },
+    /// A server or a spectator stream couldn't be reached or opened.
+    Network { addr: String, source: io::Error },
+}
+
+impl Error {
+    pub fn asset(name: &str, message: impl fmt::Display) -> Self {
+        Error::Asset {
+            name: name.to_owned(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn save(action: &'static str, source: io::Error) -> Self {
+        Error::Save { action, source }
+    }
+
+    pub fn board(from: impl fmt::Display, message: impl fmt::Display) -> Self {
+        Error::Board {
+            from: from.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn network(addr: impl fmt::Display, source: io::Error) -> Self {
+        Error::Network {
+            addr: addr.to_string(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Asset { name, message } => write!(f, "Could not load {}: {}", name, message),
+            Error::Config { path, message } => {
+                write!(f, "Bad settings in {}: {}", path.display(), message)
+            }
+            Error::Save { action, source } => write!(f, "Could not {}: {}", action, source),
+            Error::Board { from, message } => write!(f, "Could not load {}: {}", from, message),
+            Error::Network { addr, source } => write!(f, "Network error at {}: {}", addr, source),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Save { source, .. } | Error::Network { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! What every cell shows on screen, worked out from the board alone. The
+//! game only paints the tiles, so everything here runs (and is benchmarked)
+//! without a window.
+
+use crate::board::{Board, GameEvent};
+
+/// Seconds between rusts popping up per cell of distance from the fatal one.
+pub const REVEAL_STEP: f32 = 0.08;
+/// Seconds a single rust takes to pop in.
+pub const POP_DURATION: f32 = 0.25;
+
+/// How one cell looks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tile {
+    /// Still covered.
+    Covered,
+    /// Covered, with a question mark on it.
+    Marked,
+    /// Covered by `flags` flags. After a loss `wrong` marks the ones that
+    /// don't match the rusts underneath.
+    Flagged { flags: u8, wrong: bool },
+    /// How many rusts touch the cell, blank for none. Once the game is over
+    /// every safe cell shows its number.
+    Number(u8),
+    /// Rusts, shown once the game is over. After a loss they pop in one by
+    /// one and `pop` is how big they are so far, 0 until their turn comes
+    /// and overshooting 1 a little before settling.
+    Rust {
+        rusts: u8,
+        pop: f32,
+        fatal: bool,
+        won: bool,
+    },
+}
+
+/// The tiles for a whole board, laid out like `Board::cells`.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    height: usize,
+    tiles: Vec<Tile>,
+}
+
+impl Frame {
+    pub fn tile(&self, x: usize, y: usize) -> Tile {
+        self.tiles[x * self.height + y]
+    }
+}
+
+/// The tiles for `board` while it is played, or once `over`, `since_loss`
+/// seconds after the game was lost.
+pub fn prepare(board: &Board, over: Option<GameEvent>, since_loss: f32) -> Frame {
+    let (width, height) = (board.width(), board.height());
+    let fatal = match over {
+        Some(GameEvent::Failed { x, y }) => Some((x, y)),
+        _ => None,
+    };
+    let mut tiles = Vec::with_capacity(width * height);
+    for ((x, y), &cell) in board.positions().zip(board.cells()) {
+        tiles.push(match (over, fatal) {
+            (None, _) if cell.is_hidden() && cell.is_flagged() => Tile::Flagged {
+                flags: cell.flags(),
+                wrong: false,
+            },
+            (None, _) if cell.is_hidden() && cell.is_marked() => Tile::Marked,
+            (None, _) if cell.is_hidden() => Tile::Covered,
+            (Some(_), Some(_)) if cell.is_flagged() => Tile::Flagged {
+                flags: cell.flags(),
+                wrong: cell.flags() != cell.rusts(),
+            },
+            (_, fatal) if cell.is_rust() => Tile::Rust {
+                rusts: cell.rusts(),
+                pop: match fatal {
+                    Some(at) => pop(board, at, (x, y), since_loss),
+                    None => 1.0,
+                },
+                fatal: fatal == Some((x, y)),
+                won: over == Some(GameEvent::Solved),
+            },
+            _ => Tile::Number(cell.rust_count()),
+        });
+    }
+    Frame { height, tiles }
+}
+
+// after a loss the rusts pop up in rings around the fatal one
+fn pop(board: &Board, fatal: (usize, usize), at: (usize, usize), since_loss: f32) -> f32 {
+    let (width, height) = (board.width() as f32, board.height() as f32);
+    let mut dx = (at.0 as f32 - fatal.0 as f32).abs();
+    let mut dy = (at.1 as f32 - fatal.1 as f32).abs();
+    if board.variant.wrapping {
+        dx = dx.min(width - dx);
+        dy = dy.min(height - dy);
+    }
+    let delay = (dx * dx + dy * dy).sqrt() * REVEAL_STEP;
+    let t = ((since_loss - delay) / POP_DURATION).clamp(0.0, 1.0);
+    if t <= 0.0 {
+        0.0
+    } else {
+        ease_out_back(t)
+    }
+}
+
+// overshoots a little before settling, for a pop-in feel
+fn ease_out_back(t: f32) -> f32 {
+    let c1 = 1.70158;
+    let c3 = c1 + 1.0;
+    1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+}
@@ -0,0 +1,325 @@
+//! What the mouse buttons and keys do. A binding is a key or a mouse
+//! button plus the modifier keys held with it, written like `Ctrl+Left` or
+//! `Shift+H`, and the settings map bindings to the actions here.
+
+use std::fmt;
+
+// mouse buttons as the window names them
+const BUTTONS: [&str; 5] = ["Left", "Middle", "Right", "X1", "X2"];
+
+// keys other than letters, digits and function keys that can be bound
+const NAMED_KEYS: [&str; 10] = [
+    "Space",
+    "Tab",
+    "Return",
+    "Escape",
+    "Backspace",
+    "Comma",
+    "Period",
+    "Slash",
+    "Minus",
+    "Equals",
+];
+
+/// What a click on the board does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickAction {
+    Reveal,
+    Flag,
+    Chord,
+}
+
+impl ClickAction {
+    pub const ALL: [ClickAction; 3] = [ClickAction::Reveal, ClickAction::Flag, ClickAction::Chord];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ClickAction::Reveal => "reveal",
+            ClickAction::Flag => "flag",
+            ClickAction::Chord => "chord",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        ClickAction::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+}
+
+/// What a key press does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    Hint,
+    Analysis,
+    Export,
+    Spectate,
+    Load,
+    Settings,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 6] = [
+        KeyAction::Hint,
+        KeyAction::Analysis,
+        KeyAction::Export,
+        KeyAction::Spectate,
+        KeyAction::Load,
+        KeyAction::Settings,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyAction::Hint => "hint",
+            KeyAction::Analysis => "analysis",
+            KeyAction::Export => "export",
+            KeyAction::Spectate => "spectate",
+            KeyAction::Load => "load",
+            KeyAction::Settings => "settings",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        KeyAction::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+
+    pub fn default_key(self) -> &'static str {
+        match self {
+            KeyAction::Hint => "H",
+            KeyAction::Analysis => "A",
+            KeyAction::Export => "E",
+            KeyAction::Spectate => "S",
+            KeyAction::Load => "L",
+            KeyAction::Settings => "O",
+        }
+    }
+}
+
+/// Whether `name` is a key that can be bound: `A` to `Z`, `Num0` to
+/// `Num9`, `F1` to `F12` or one of a few named keys such as `Space`.
+pub fn is_key_name(name: &str) -> bool {
+    let numbered = |prefix: &str, first: u32, last: u32| {
+        (first..=last).any(|n| name == format!("{}{}", prefix, n))
+    };
+    (name.len() == 1 && name.chars().all(|c| c.is_ascii_uppercase()))
+        || numbered("Num", 0, 9)
+        || numbered("F", 1, 12)
+        || NAMED_KEYS.contains(&name)
+}
+
+/// Whether `name` is a mouse button that can be bound.
+pub fn is_button_name(name: &str) -> bool {
+    BUTTONS.contains(&name)
+}
+
+/// The modifier keys held down or bound, either side counting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+/// The modifier keys down right now, left and right apart so letting go of
+/// one Ctrl doesn't lose the other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeldKeys {
+    // left side first
+    ctrl: [bool; 2],
+    shift: [bool; 2],
+    alt: [bool; 2],
+}
+
+impl HeldKeys {
+    /// Follows `key` going down or up, and says whether it was a modifier
+    /// at all. Keys are named as the window names them, such as `LCtrl`.
+    pub fn track(&mut self, key: &str, down: bool) -> bool {
+        let held = match key {
+            "LCtrl" => &mut self.ctrl[0],
+            "RCtrl" => &mut self.ctrl[1],
+            "LShift" => &mut self.shift[0],
+            "RShift" => &mut self.shift[1],
+            "LAlt" => &mut self.alt[0],
+            "RAlt" => &mut self.alt[1],
+            _ => return false,
+        };
+        *held = down;
+        true
+    }
+
+    /// What is held, whichever side it is on.
+    pub fn modifiers(&self) -> Modifiers {
+        Modifiers {
+            ctrl: self.ctrl[0] || self.ctrl[1],
+            shift: self.shift[0] || self.shift[1],
+            alt: self.alt[0] || self.alt[1],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub modifiers: Modifiers,
+    /// A key or a mouse button, depending on what is being bound.
+    pub input: String,
+}
+
+impl Binding {
+    pub fn new(input: &str, modifiers: Modifiers) -> Self {
+        Binding {
+            modifiers,
+            input: input.to_owned(),
+        }
+    }
+
+    /// Reads a key binding such as `Ctrl+H`.
+    pub fn key(text: &str) -> Option<Self> {
+        Binding::parse(text, is_key_name)
+    }
+
+    /// Reads a mouse binding such as `Ctrl+Left`.
+    pub fn button(text: &str) -> Option<Self> {
+        Binding::parse(text, is_button_name)
+    }
+
+    fn parse(text: &str, valid: fn(&str) -> bool) -> Option<Self> {
+        let mut parts: Vec<&str> = text.split('+').collect();
+        let input = parts.pop().filter(|input| valid(input))?;
+        let mut modifiers = Modifiers::default();
+        for part in parts {
+            let held = match part {
+                "Ctrl" => &mut modifiers.ctrl,
+                "Shift" => &mut modifiers.shift,
+                "Alt" => &mut modifiers.alt,
+                _ => return None,
+            };
+            *held = true;
+        }
+        Some(Binding::new(input, modifiers))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        write!(f, "{}", self.input)
+    }
+}
+
+/// The action bound to `input` with exactly the modifiers `held`, or
+/// failing that to `input` on its own, so a stray Shift doesn't swallow a
+/// click.
+pub fn lookup<T: Copy>(bindings: &[(Binding, T)], input: &str, held: Modifiers) -> Option<T> {
+    let find = |modifiers: Modifiers| {
+        bindings
+            .iter()
+            .find(|(binding, _)| binding.input == input && binding.modifiers == modifiers)
+            .map(|&(_, action)| action)
+    };
+    find(held).or_else(|| find(Modifiers::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held(ctrl: bool, shift: bool, alt: bool) -> Modifiers {
+        Modifiers { ctrl, shift, alt }
+    }
+
+    #[test]
+    fn bindings_read_and_write_the_same() {
+        let binding = Binding::key("Ctrl+Shift+Alt+F5").unwrap();
+        assert_eq!(binding.input, "F5");
+        assert_eq!(binding.modifiers, held(true, true, true));
+        assert_eq!(binding.to_string(), "Ctrl+Shift+Alt+F5");
+        // modifiers come out in one order however they went in
+        assert_eq!(
+            Binding::key("Alt+Ctrl+H").unwrap().to_string(),
+            "Ctrl+Alt+H"
+        );
+        for text in &["H", "Num7", "Space", "Shift+Comma"] {
+            assert_eq!(Binding::key(text).unwrap().to_string(), *text);
+        }
+        for text in &["Left", "Ctrl+Left", "Shift+Right", "Alt+X2"] {
+            assert_eq!(Binding::button(text).unwrap().to_string(), *text);
+        }
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        for text in &[
+            "",
+            "h",
+            "Hat",
+            "F13",
+            "Num10",
+            "Ctrl+",
+            "Meta+H",
+            "Ctrl+Left",
+            "H+Ctrl",
+        ] {
+            assert_eq!(Binding::key(text), None, "{}", text);
+        }
+        for text in &["", "H", "Back", "Ctrl+left", "Super+Left"] {
+            assert_eq!(Binding::button(text), None, "{}", text);
+        }
+        assert_eq!(KeyAction::from_name("cheat"), None);
+        assert_eq!(ClickAction::from_name("Reveal"), None);
+    }
+
+    #[test]
+    fn lookup_prefers_the_exact_modifiers() {
+        let bindings = vec![
+            (Binding::button("Left").unwrap(), ClickAction::Reveal),
+            (Binding::button("Ctrl+Left").unwrap(), ClickAction::Flag),
+            (Binding::button("Middle").unwrap(), ClickAction::Chord),
+        ];
+        let none = Modifiers::default();
+        assert_eq!(lookup(&bindings, "Left", none), Some(ClickAction::Reveal));
+        assert_eq!(
+            lookup(&bindings, "Left", held(true, false, false)),
+            Some(ClickAction::Flag)
+        );
+        // nothing bound to Shift+Left, so the plain click stands
+        assert_eq!(
+            lookup(&bindings, "Left", held(false, true, false)),
+            Some(ClickAction::Reveal)
+        );
+        assert_eq!(lookup(&bindings, "Right", none), None);
+    }
+
+    #[test]
+    fn modifiers_follow_either_side() {
+        let mut keys = HeldKeys::default();
+        assert!(keys.track("RCtrl", true));
+        assert!(keys.track("LShift", true));
+        assert!(!keys.track("H", true));
+        assert_eq!(keys.modifiers(), held(true, true, false));
+        assert!(keys.track("RCtrl", false));
+        assert_eq!(keys.modifiers(), held(false, true, false));
+    }
+
+    #[test]
+    fn letting_go_of_one_side_keeps_the_other() {
+        let mut keys = HeldKeys::default();
+        keys.track("LCtrl", true);
+        keys.track("RCtrl", true);
+        keys.track("LCtrl", false);
+        assert_eq!(keys.modifiers(), held(true, false, false));
+        keys.track("RCtrl", false);
+        assert_eq!(keys.modifiers(), Modifiers::default());
+    }
+}
@@ -0,0 +1,19 @@
+pub mod board;
+pub mod cell;
+pub mod daily;
+pub mod error;
+pub mod frame;
+pub mod input;
+pub mod net;
+pub mod player;
+pub mod puzzle;
+pub mod rpc;
+pub mod settings;
+pub mod solver;
+pub mod stats;
+pub mod storage;
+pub mod text;
+pub mod variant;
+pub mod versus;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -1,24 +1,140 @@
 use ggez::conf::{WindowMode, WindowSetup};
-use ggez::event::{self, MouseButton};
+use ggez::event::{self, Keycode, Mod, MouseButton, MouseState};
 use ggez::graphics;
 use ggez::graphics::{DrawParam, Mesh, Point2};
-use ggez::{Context, ContextBuilder, GameResult};
-use std::{env, path};
+use ggez::{timer, Context, ContextBuilder, GameResult};
+use std::fs;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, path, process};
 
 const DEFAULT_CELL_DIMS: f32 = 80.0;
 const DEFAULT_CELLS_ROW: usize = 9;
 const CTRL_PANEL_WIDTH: f32 = 350.0;
-const EASY: (usize, usize) = (10, 9);
-const MEDIUM: (usize, usize) = (37, 18);
-const HARD: (usize, usize) = (82, 27);
+const FONT: &str = "/FiraCode-Bold.ttf";
+// games shown in the chart on the stats screen
+const RECENT_GAMES: usize = 20;
+// how often the settings file is checked for changes made by hand
+const SETTINGS_POLL: Duration = Duration::from_secs(1);
+// how long a notice stays along the bottom of the board
+const NOTICE_TIME: Duration = Duration::from_secs(4);
 
-mod board;
-mod cell;
-use crate::board::Board;
+use minesweeper::board::{Board, GameEvent, EASY, HARD, MEDIUM};
+use minesweeper::daily::{self, DailyResult, Date};
+use minesweeper::error::Error;
+use minesweeper::frame::{self, Tile};
+use minesweeper::input::{self, Binding, ClickAction, HeldKeys, KeyAction};
+use minesweeper::net::{self, Broadcaster, Client, ClientMessage, NetMode, ServerMessage};
+use minesweeper::player::Move;
+use minesweeper::puzzle::{self, PUZZLES};
+use minesweeper::settings::{self, Chording, SafeStart, Settings};
+use minesweeper::solver::{self, Analysis, Hint};
+use minesweeper::stats::{self, Clicks, GameRecord, Summary};
+use minesweeper::storage;
+use minesweeper::text;
+use minesweeper::variant::{Neighborhood, Variant};
+use minesweeper::versus::{Finish, Scoring, Versus};
 
+// what the area left of the control panel shows
+#[derive(Clone, Copy, PartialEq)]
+enum Screen {
+    Game,
+    Stats,
+    Settings,
+}
+
+// one line of the settings screen, each changed by clicking it
+#[derive(Clone, Copy, PartialEq)]
+enum SettingRow {
+    Theme,
+    Difficulty,
+    SafeStart,
+    QuestionMarks,
+    Chording,
+    AnimationSpeed,
+    Click(ClickAction),
+    Key(KeyAction),
+}
+
+impl SettingRow {
+    fn all() -> Vec<SettingRow> {
+        let mut rows = vec![
+            SettingRow::Theme,
+            SettingRow::Difficulty,
+            SettingRow::SafeStart,
+            SettingRow::QuestionMarks,
+            SettingRow::Chording,
+            SettingRow::AnimationSpeed,
+        ];
+        rows.extend(
+            ClickAction::ALL
+                .iter()
+                .map(|&action| SettingRow::Click(action)),
+        );
+        rows.extend(KeyAction::ALL.iter().map(|&action| SettingRow::Key(action)));
+        rows
+    }
+
+    // where the row sits on the settings screen
+    fn rect(index: usize) -> graphics::Rect {
+        graphics::Rect::new(40.0, 90.0 + index as f32 * 40.0, 640.0, 34.0)
+    }
+}
+
+// where the board came from, which decides how reset sets it up again and
+// what is kept once the game ends
+#[derive(Clone, PartialEq)]
+enum Mode {
+    Classic,
+    // today's shared board. Only the first attempt each day is scored, any
+    // replay after that is practice
+    Daily {
+        date: Date,
+        scored: bool,
+    },
+    // a board from a file, read again on reset
+    File(path::PathBuf),
+    // one of the bundled puzzles, `clean` until a move the board didn't
+    // prove or any help from the hints or the analysis
+    Puzzle {
+        index: usize,
+        clean: bool,
+    },
+    // two players taking turns at the mouse
+    Versus(Versus),
+    // playing on a server, which owns the board. Ours only mirrors what
+    // the server shows us, so the solver can't help here
+    Online {
+        player: usize,
+        net_mode: NetMode,
+        rusts: usize,
+        // race opponents and how much of their board they have cleared
+        rivals: Vec<(usize, f32)>,
+        winner: Option<usize>,
+    },
+    // following someone else's game, read-only. The clock is theirs,
+    // `clock` seconds as of `clock_at` and ticking on from there if running
+    Watching {
+        rusts: usize,
+        cursor: Option<(f32, f32)>,
+        clock: f64,
+        running: bool,
+        clock_at: Instant,
+    },
+}
+
+// our game streamed to anyone watching, and what they have been sent so far
+struct Spectators {
+    broadcaster: Broadcaster,
+    board: Board,
+    running: bool,
+    cursor: Option<(f32, f32)>,
+}
+
+#[derive(Clone, Copy)]
 enum GameOverState {
     Solved,
-    Failed,
+    // remembers which rust ended the game
+    Failed { x: usize, y: usize },
 }
 
 // First we make a structure to contain the game's state
@@ -28,9 +144,45 @@ struct MainState {
     image: graphics::Image,
     flag: graphics::Image,
     font: graphics::Font,
+    small_font: graphics::Font,
     num_font: graphics::Font,
     happy_image: graphics::Image,
     game_over: Option<GameOverState>,
+    // seconds since the game was lost, drives the rust reveal
+    loss_elapsed: f32,
+    // the solver's suggestion, shown until the next move
+    hint: Option<Hint>,
+    hints_used: usize,
+    // probability heatmap for training, worked out again after every move
+    analysis_mode: bool,
+    analysis: Option<Analysis>,
+    // timed from the first click on the board to the end of the game
+    started_at: Option<Instant>,
+    clicks: Clicks,
+    three_bv: usize,
+    // the finished game, for the summary
+    record: Option<GameRecord>,
+    screen: Screen,
+    // every finished game, loaded when the stats screen opens
+    history: Vec<GameRecord>,
+    mode: Mode,
+    net: Option<Client>,
+    spectators: Option<Spectators>,
+    // the mouse over the board, in cells from its top left corner
+    cursor: Option<(f32, f32)>,
+    // the last thing that went wrong, shown until the next click or key
+    error: Option<Error>,
+    // something worth telling the player that isn't a problem, like where
+    // an export went, and when it was said
+    notice: Option<(String, Instant)>,
+    settings: Settings,
+    // when the settings file was last changed, and last looked at
+    settings_modified: Option<SystemTime>,
+    settings_checked: Instant,
+    // the row of the settings screen waiting for a key or a click
+    rebinding: Option<SettingRow>,
+    // modifier keys held down, which change what keys and clicks do
+    held: HeldKeys,
     did_sleep: bool,
     did_reveal: bool,
     first_click: bool,
@@ -38,17 +190,58 @@ struct MainState {
     easy_button: graphics::Rect,
     medium_button: graphics::Rect,
     hard_button: graphics::Rect,
+    wrap_button: graphics::Rect,
+    status_rect: graphics::Rect,
+    neighborhood_button: graphics::Rect,
+    rusts_button: graphics::Rect,
+    hint_button: graphics::Rect,
+    analysis_button: graphics::Rect,
+    stats_button: graphics::Rect,
+    daily_button: graphics::Rect,
+    puzzle_button: graphics::Rect,
+    versus_button: graphics::Rect,
+    export_button: graphics::Rect,
     mesh: Mesh,
     difficulty: (usize, usize),
+    variant: Variant,
     scaling: f32,
 }
 
+fn load_image(ctx: &mut Context, path: &str) -> Result<graphics::Image, Error> {
+    graphics::Image::new(ctx, path).map_err(|e| Error::asset(path, e))
+}
+
+fn load_font(ctx: &mut Context, size: u32) -> Result<graphics::Font, Error> {
+    graphics::Font::new(ctx, FONT, size).map_err(|e| Error::asset(FONT, e))
+}
+
+fn rgba((r, g, b): (u8, u8, u8), a: u8) -> graphics::Color {
+    graphics::Color::from_rgba(r, g, b, a)
+}
+
+// breaks `text` into lines of at most `width` characters, between words
+// where it can
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + word.len() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_owned()),
+        }
+    }
+    lines
+}
+
 impl MainState {
-    pub fn new(ctx: &mut Context) -> GameResult<MainState> {
-        let flag = graphics::Image::new(ctx, "/nope_square.resized.jpg").unwrap();
-        let font = graphics::Font::new(ctx, "/FiraCode-Bold.ttf", 30)?;
-        let happy_image = graphics::Image::new(ctx, "/ferris_happy.resized.jpg").unwrap();
-        let image = graphics::Image::new(ctx, "/cpp.resized.jpg").unwrap();
+    pub fn new(ctx: &mut Context) -> Result<MainState, Error> {
+        let flag = load_image(ctx, "/nope_square.resized.jpg")?;
+        let font = load_font(ctx, 30)?;
+        let small_font = load_font(ctx, 20)?;
+        let happy_image = load_image(ctx, "/ferris_happy.resized.jpg")?;
+        let image = load_image(ctx, "/cpp.resized.jpg")?;
 
         // based on difficulty
         let dims = DEFAULT_CELL_DIMS * DEFAULT_CELLS_ROW as f32;
@@ -57,6 +250,19 @@ impl MainState {
         let medium_button = graphics::Rect::new(dims + 75.0, 150.0, 250.0, 75.0);
         let hard_button = graphics::Rect::new(dims + 75.0, 250.0, 250.0, 75.0);
         let reset_button = graphics::Rect::new(dims + 75.0, 400.0, 250.0, 75.0);
+        // game status sits in the gap above the reset button
+        let status_rect = graphics::Rect::new(dims + 75.0, 335.0, 250.0, 55.0);
+        // smaller toggles for the game variants, two to a row
+        let wrap_button = graphics::Rect::new(dims + 75.0, 495.0, 120.0, 40.0);
+        let neighborhood_button = graphics::Rect::new(dims + 205.0, 495.0, 120.0, 40.0);
+        let rusts_button = graphics::Rect::new(dims + 75.0, 540.0, 120.0, 40.0);
+        let hint_button = graphics::Rect::new(dims + 205.0, 540.0, 120.0, 40.0);
+        let analysis_button = graphics::Rect::new(dims + 75.0, 585.0, 120.0, 40.0);
+        let stats_button = graphics::Rect::new(dims + 205.0, 585.0, 120.0, 40.0);
+        let daily_button = graphics::Rect::new(dims + 75.0, 630.0, 120.0, 40.0);
+        let export_button = graphics::Rect::new(dims + 205.0, 630.0, 120.0, 40.0);
+        let puzzle_button = graphics::Rect::new(dims + 75.0, 675.0, 120.0, 40.0);
+        let versus_button = graphics::Rect::new(dims + 205.0, 675.0, 120.0, 40.0);
 
         // based on difficulty
         let mut mb = graphics::MeshBuilder::new();
@@ -76,18 +282,49 @@ impl MainState {
             }
         }
 
-        let mesh = mb.build(ctx).unwrap();
-        let mut board = Board::new(EASY);
+        let mesh = mb.build(ctx).map_err(|e| Error::asset("the grid", e))?;
+        // bad settings are shown once the window is up, the defaults
+        // stand in meanwhile
+        let (settings, settings_error) = match settings::load() {
+            Ok(settings) => (settings, None),
+            Err(e) => (Settings::default(), Some(e)),
+        };
+        let mut board = Board::new(settings.difficulty, Variant::default());
         board.calculate_rust_count();
+        let three_bv = board.three_bv();
 
-        Ok(MainState {
+        let mut state = MainState {
             // frames: 0,
             board,
             image,
             flag,
             font: font.clone(),
+            small_font,
             num_font: font,
             game_over: None,
+            loss_elapsed: 0.0,
+            hint: None,
+            hints_used: 0,
+            analysis_mode: false,
+            analysis: None,
+            started_at: None,
+            clicks: Clicks::default(),
+            three_bv,
+            record: None,
+            screen: Screen::Game,
+            history: vec![],
+            mode: Mode::Classic,
+            net: None,
+            spectators: None,
+            cursor: None,
+            error: None,
+            notice: None,
+            difficulty: settings.difficulty,
+            settings,
+            settings_modified: settings::modified(),
+            settings_checked: Instant::now(),
+            rebinding: None,
+            held: HeldKeys::default(),
             did_sleep: false,
             did_reveal: false,
             first_click: true,
@@ -96,20 +333,378 @@ impl MainState {
             easy_button,
             medium_button,
             hard_button,
+            wrap_button,
+            status_rect,
+            neighborhood_button,
+            rusts_button,
+            hint_button,
+            analysis_button,
+            stats_button,
+            daily_button,
+            puzzle_button,
+            versus_button,
+            export_button,
             mesh,
-            difficulty: EASY,
+            variant: Variant::default(),
             scaling: 1.0,
-        })
+        };
+        if let Some(e) = settings_error {
+            state.report(e);
+        }
+        Ok(state)
     }
 
-    fn reset(&mut self, ctx: &mut Context, difficulty: (usize, usize)) -> GameResult<()> {
-        let mut board = Board::new(difficulty);
+    fn reset(&mut self, ctx: &mut Context, difficulty: (usize, usize)) -> Result<(), Error> {
+        let mut board = Board::new(difficulty, self.variant);
         board.calculate_rust_count();
-        let scaling = DEFAULT_CELLS_ROW as f32 / difficulty.1 as f32;
-        let (_, rows) = difficulty;
+        self.mode = Mode::Classic;
+        self.start(ctx, board)
+    }
+
+    // a board from a file in the text format. Its layout is fixed, so the
+    // first click is not made safe and the game stays out of the stats
+    fn load(&mut self, ctx: &mut Context, path: &path::Path) -> Result<(), Error> {
+        let board = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| text::parse(&contents).map_err(|e| e.to_string()))
+            .map_err(|e| Error::board(path.display(), e))?;
+        self.variant = board.variant;
+        self.start(ctx, board)?;
+        self.mode = Mode::File(path.to_owned());
+        self.first_click = false;
+        if let Some(event) = self.board.outcome {
+            self.on_game_event(event);
+        }
+        Ok(())
+    }
+
+    // writes the board as it stands to a new file in the data directory
+    fn export(&mut self) {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let written = storage::data_file(&format!("board-{}.txt", secs))
+            .and_then(|path| fs::write(&path, text::write(&self.board)).map(|_| path));
+        match written {
+            Ok(path) => self.notify(format!("BOARD EXPORTED TO {}", path.display())),
+            Err(e) => self.report(Error::save("export the board", e)),
+        }
+    }
+
+    // today's board, the same for every player. It is always classic, so
+    // the variant toggles go back to their defaults
+    fn start_daily(&mut self, ctx: &mut Context) -> Result<(), Error> {
+        let date = Date::today();
+        let scored = match daily::attempted(date) {
+            Ok(attempted) => !attempted,
+            Err(e) => {
+                self.report(Error::save("load daily results", e));
+                false
+            }
+        };
+        self.variant = Variant::default();
+        self.start(ctx, date.board())?;
+        self.mode = Mode::Daily { date, scored };
+        Ok(())
+    }
+
+    // a bundled puzzle, with its cells uncovered as the puzzle says
+    fn start_puzzle(&mut self, ctx: &mut Context, index: usize) -> Result<(), Error> {
+        let board = PUZZLES[index]
+            .board()
+            .map_err(|e| Error::board(format!("puzzle {}", PUZZLES[index].name), e))?;
+        self.variant = board.variant;
+        self.start(ctx, board)?;
+        self.mode = Mode::Puzzle { index, clean: true };
+        self.first_click = false;
+        Ok(())
+    }
+
+    // a fresh board at the current settings for two players
+    fn start_versus(&mut self, ctx: &mut Context, scoring: Scoring) -> Result<(), Error> {
+        let mut board = Board::new(self.difficulty, self.variant);
+        board.calculate_rust_count();
+        self.start(ctx, board)?;
+        self.mode = Mode::Versus(Versus::new(scoring));
+        Ok(())
+    }
+
+    // joins a multiplayer server. The board arrives with the server's
+    // first messages, until then the current one stays up
+    fn connect(&mut self, addr: &str, name: &str) {
+        match Client::connect(addr, name) {
+            Ok(client) => {
+                self.net = Some(client);
+                self.mode = Mode::Online {
+                    player: 0,
+                    net_mode: NetMode::Coop,
+                    rusts: 0,
+                    rivals: vec![],
+                    winner: None,
+                };
+            }
+            Err(e) => self.report(Error::network(addr, e)),
+        }
+    }
+
+    // follows a game streamed from another window
+    fn watch(&mut self, addr: &str) {
+        match Client::connect(addr, "spectator") {
+            Ok(client) => {
+                self.net = Some(client);
+                self.mode = Mode::Watching {
+                    rusts: 0,
+                    cursor: None,
+                    clock: 0.0,
+                    running: false,
+                    clock_at: Instant::now(),
+                };
+            }
+            Err(e) => self.report(Error::network(addr, e)),
+        }
+    }
+
+    // starts or stops streaming this game to spectators
+    fn toggle_spectators(&mut self) {
+        if self.spectators.take().is_some() {
+            self.notify("STOPPED STREAMING");
+            return;
+        }
+        match Broadcaster::bind(net::SPECTATE_PORT) {
+            Ok(broadcaster) => {
+                self.notify(format!("STREAMING ON 127.0.0.1:{}", net::SPECTATE_PORT));
+                self.spectators = Some(Spectators {
+                    broadcaster,
+                    board: self.board.clone(),
+                    running: false,
+                    cursor: None,
+                });
+            }
+            Err(e) => self.report(Error::network(
+                format!("127.0.0.1:{}", net::SPECTATE_PORT),
+                e,
+            )),
+        }
+    }
+
+    // sends spectators whatever changed since last frame, and newcomers
+    // the whole game so far
+    fn publish(&mut self) {
+        let running = self.started_at.is_some() && self.game_over.is_none();
+        let clock = ServerMessage::Clock {
+            seconds: self.elapsed(),
+            running,
+        };
+        let cursor = self.cursor;
+        let board = &self.board;
+        let spectators = match &mut self.spectators {
+            Some(spectators) => spectators,
+            None => return,
+        };
+        spectators.broadcaster.accept(|| {
+            let mut greeting = net::snapshot(board, 0);
+            greeting.push(clock.clone());
+            greeting.extend(cursor.map(|(x, y)| ServerMessage::Cursor { x, y }));
+            greeting
+        });
+        let mut messages = vec![];
+        let changes = net::catch_up(&spectators.board, board, 0);
+        let restarted = matches!(changes.first(), Some(ServerMessage::Board { .. }));
+        // the clock goes first so it has stopped by the time the game ends
+        if running != spectators.running || restarted {
+            messages.push(clock);
+            spectators.running = running;
+        }
+        if !changes.is_empty() {
+            messages.extend(changes);
+            spectators.board = board.clone();
+        }
+        if cursor != spectators.cursor {
+            messages.extend(cursor.map(|(x, y)| ServerMessage::Cursor { x, y }));
+            spectators.cursor = cursor;
+        }
+        spectators.broadcaster.send(&messages);
+    }
+
+    fn on_server_message(
+        &mut self,
+        ctx: &mut Context,
+        message: ServerMessage,
+    ) -> Result<(), Error> {
+        let me = match self.mode {
+            Mode::Online { player, .. } => player,
+            Mode::Watching { .. } => 0,
+            _ => return Ok(()),
+        };
+        match message {
+            ServerMessage::Welcome { player, mode } => {
+                if let Mode::Online {
+                    player: ref mut me,
+                    ref mut net_mode,
+                    ..
+                } = self.mode
+                {
+                    *me = player;
+                    *net_mode = mode;
+                }
+            }
+            ServerMessage::Board {
+                width,
+                height,
+                rusts,
+                variant,
+            } => {
+                self.variant = variant;
+                self.start(ctx, net::blank_board(width, height, variant))?;
+                self.first_click = false;
+                if let Mode::Online {
+                    rusts: ref mut total,
+                    ref mut rivals,
+                    ref mut winner,
+                    ..
+                } = self.mode
+                {
+                    *total = rusts;
+                    rivals.clear();
+                    *winner = None;
+                }
+                if let Mode::Watching {
+                    rusts: ref mut total,
+                    ..
+                } = self.mode
+                {
+                    *total = rusts;
+                }
+            }
+            ServerMessage::Over { player, event } => {
+                let shared = matches!(
+                    self.mode,
+                    Mode::Online {
+                        net_mode: NetMode::Coop,
+                        ..
+                    } | Mode::Watching { .. }
+                );
+                if (shared || player == me) && self.game_over.is_none() {
+                    self.board.outcome = Some(event);
+                    self.on_game_event(event);
+                }
+            }
+            ServerMessage::Progress {
+                player,
+                revealed,
+                safe,
+            } => {
+                if let Mode::Online { ref mut rivals, .. } = self.mode {
+                    if player != me {
+                        let progress = revealed as f32 / safe.max(1) as f32;
+                        match rivals.iter_mut().find(|(rival, _)| *rival == player) {
+                            Some(rival) => rival.1 = progress,
+                            None => rivals.push((player, progress)),
+                        }
+                    }
+                }
+            }
+            ServerMessage::Winner { player } => {
+                if let Mode::Online { ref mut winner, .. } = self.mode {
+                    *winner = Some(player);
+                }
+            }
+            ServerMessage::Joined { player, name } => {
+                self.notify(format!("P{} {} JOINED", player + 1, name))
+            }
+            ServerMessage::Left { player } => {
+                if let Mode::Online { ref mut rivals, .. } = self.mode {
+                    rivals.retain(|(rival, _)| *rival != player);
+                }
+                self.notify(format!("P{} LEFT", player + 1));
+            }
+            ServerMessage::Cursor { x, y } => {
+                if let Mode::Watching { ref mut cursor, .. } = self.mode {
+                    *cursor = Some((x, y));
+                }
+            }
+            ServerMessage::Clock { seconds, running } => {
+                if let Mode::Watching {
+                    ref mut clock,
+                    running: ref mut ticking,
+                    ref mut clock_at,
+                    ..
+                } = self.mode
+                {
+                    *clock = seconds;
+                    *ticking = running;
+                    *clock_at = Instant::now();
+                }
+            }
+            ServerMessage::Error { message } => self.notify(format!("SERVER: {}", message)),
+            message => net::apply(&mut self.board, &message),
+        }
+        Ok(())
+    }
+
+    // rusts in play, which an online board doesn't know until it's over
+    fn total_rusts(&self) -> usize {
+        match self.mode {
+            Mode::Online { rusts, .. } | Mode::Watching { rusts, .. } => rusts,
+            _ => self.board.total_rusts(),
+        }
+    }
+
+    // the puzzle after the current one, or the first one not solved yet
+    fn next_puzzle(&mut self) -> usize {
+        if let Mode::Puzzle { index, .. } = self.mode {
+            return (index + 1) % PUZZLES.len();
+        }
+        let solved = match puzzle::solved() {
+            Ok(solved) => solved,
+            Err(e) => {
+                self.report(Error::save("load solved puzzles", e));
+                vec![]
+            }
+        };
+        PUZZLES
+            .iter()
+            .position(|p| !solved.iter().any(|name| name == p.name))
+            .unwrap_or(0)
+    }
+
+    // hints and the heatmap do the thinking a puzzle asks for
+    fn assisted(&mut self) {
+        if let Mode::Puzzle { ref mut clean, .. } = self.mode {
+            *clean = false;
+        }
+    }
+
+    // shows what went wrong over the board. The game carries on as it was
+    fn report(&mut self, error: Error) {
+        self.error = Some(error);
+    }
+
+    // puts `message` along the bottom of the board for a few seconds,
+    // replacing whatever was there
+    fn notify(&mut self, message: impl Into<String>) {
+        self.notice = Some((message.into(), Instant::now()));
+    }
+
+    fn report_failure(&mut self, result: Result<(), Error>) {
+        if let Err(e) = result {
+            self.report(e);
+        }
+    }
+
+    // sets up a fresh game on `board`, sizing the grid to fit it
+    fn start(&mut self, ctx: &mut Context, board: Board) -> Result<(), Error> {
+        let difficulty = board.difficulty;
+        let three_bv = board.three_bv();
+        // wrapping boards get a ghost row/column on every side
+        let margin = if board.variant.wrapping { 1 } else { 0 };
+        let cols = board.width() + 2 * margin;
+        let rows = board.height() + 2 * margin;
+        let scaling = DEFAULT_CELLS_ROW as f32 / cols.max(rows) as f32;
         let mut mb = graphics::MeshBuilder::new();
-        let font = graphics::Font::new(ctx, "/FiraCode-Bold.ttf", (30.0 * scaling) as u32)?;
-        for i in 0..rows {
+        let font = load_font(ctx, (30.0 * scaling) as u32)?;
+        for i in 0..cols {
             for j in 0..rows {
                 let x1 = i as f32 * DEFAULT_CELL_DIMS as f32 * scaling;
                 let x2 =
@@ -127,10 +722,18 @@ impl MainState {
             }
         }
 
-        let mesh = mb.build(ctx).unwrap();
+        let mesh = mb.build(ctx).map_err(|e| Error::asset("the grid", e))?;
 
         self.board = board;
         self.game_over = None;
+        self.loss_elapsed = 0.0;
+        self.hint = None;
+        self.hints_used = 0;
+        self.analysis = None;
+        self.started_at = None;
+        self.clicks = Clicks::default();
+        self.three_bv = three_bv;
+        self.record = None;
         self.did_sleep = false;
         self.did_reveal = false;
         self.first_click = true;
@@ -142,6 +745,818 @@ impl MainState {
         Ok(())
     }
 
+    // the board tells us the moment a move wins or loses the game
+    fn on_game_event(&mut self, event: GameEvent) {
+        self.game_over = Some(match event {
+            GameEvent::Solved => GameOverState::Solved,
+            GameEvent::Failed { x, y } => GameOverState::Failed { x, y },
+        });
+        self.loss_elapsed = 0.0;
+
+        let record = GameRecord::new(
+            self.difficulty,
+            self.variant,
+            event == GameEvent::Solved,
+            self.elapsed(),
+            self.three_bv,
+            self.clicks,
+            self.hints_used,
+        );
+        match self.mode {
+            Mode::Classic | Mode::Daily { scored: true, .. } => {
+                if let Err(e) = stats::append(&record) {
+                    self.report(Error::save("save stats", e));
+                }
+            }
+            Mode::Puzzle { index, clean: true } if record.won => {
+                if let Err(e) = puzzle::mark_solved(PUZZLES[index].name) {
+                    self.report(Error::save("save the solved puzzle", e));
+                }
+            }
+            _ => (),
+        }
+        if let Mode::Daily { date, scored: true } = self.mode {
+            let result = DailyResult {
+                won: Some(record.won),
+                seconds: record.seconds,
+                clicks: record.clicks.total(),
+                ..DailyResult::started(date)
+            };
+            if let Err(e) = daily::record(&result) {
+                self.report(Error::save("save the daily result", e));
+            }
+            let share = result.share_string();
+            match daily::export_share(&result) {
+                Ok(path) => self.notify(format!("{} (SAVED TO {})", share, path.display())),
+                Err(e) => {
+                    self.notify(share);
+                    self.report(Error::save("save the share string", e));
+                }
+            }
+        }
+        self.record = Some(record);
+    }
+
+    // seconds on the clock, frozen once the game is over
+    fn elapsed(&self) -> f64 {
+        if let Mode::Watching {
+            clock,
+            running,
+            clock_at,
+            ..
+        } = self.mode
+        {
+            return if running {
+                clock + clock_at.elapsed().as_secs_f64()
+            } else {
+                clock
+            };
+        }
+        match (&self.record, self.started_at) {
+            (Some(record), _) => record.seconds,
+            (None, Some(started_at)) => started_at.elapsed().as_secs_f64(),
+            (None, None) => 0.0,
+        }
+    }
+
+    fn toggle_stats(&mut self) {
+        if self.screen == Screen::Stats {
+            self.screen = Screen::Game;
+            return;
+        }
+        self.history = match stats::load() {
+            Ok(history) => history,
+            Err(e) => {
+                self.report(Error::save("load stats", e));
+                vec![]
+            }
+        };
+        self.screen = Screen::Stats;
+    }
+
+    fn toggle_settings(&mut self) {
+        self.rebinding = None;
+        self.screen = if self.screen == Screen::Settings {
+            Screen::Game
+        } else {
+            Screen::Settings
+        };
+    }
+
+    // puts new settings to use. A classic game nobody has touched yet
+    // moves to the new default board
+    fn apply_settings(&mut self, ctx: &mut Context, settings: Settings) -> Result<(), Error> {
+        let difficulty = settings.difficulty;
+        let untouched = self.mode == Mode::Classic && self.started_at.is_none();
+        let moved = difficulty != self.settings.difficulty;
+        self.settings = settings;
+        if moved && untouched {
+            self.reset(ctx, difficulty)?;
+        }
+        Ok(())
+    }
+
+    // saves settings changed on the settings screen, then applies them
+    fn change_settings(&mut self, ctx: &mut Context, settings: Settings) {
+        let result = settings::save(&settings).and_then(|_| self.apply_settings(ctx, settings));
+        // our own write is not an edit to pick up again
+        self.settings_modified = settings::modified();
+        self.report_failure(result);
+    }
+
+    // picks up edits made to the settings file while the game runs
+    fn poll_settings(&mut self, ctx: &mut Context) {
+        if self.settings_checked.elapsed() < SETTINGS_POLL {
+            return;
+        }
+        self.settings_checked = Instant::now();
+        let modified = settings::modified();
+        if modified != self.settings_modified {
+            self.settings_modified = modified;
+            let result = settings::load().and_then(|settings| self.apply_settings(ctx, settings));
+            self.report_failure(result);
+        }
+    }
+
+    fn change_setting(&mut self, ctx: &mut Context, row: SettingRow) {
+        let mut settings = self.settings.clone();
+        match row {
+            SettingRow::Theme => settings.theme = settings.theme.next(),
+            SettingRow::Difficulty => {
+                settings.difficulty = match settings.difficulty {
+                    EASY => MEDIUM,
+                    MEDIUM => HARD,
+                    _ => EASY,
+                }
+            }
+            SettingRow::SafeStart => settings.safe_start = settings.safe_start.next(),
+            SettingRow::QuestionMarks => settings.question_marks = !settings.question_marks,
+            SettingRow::Chording => settings.chording = settings.chording.next(),
+            SettingRow::AnimationSpeed => settings.animation_speed = settings.next_speed(),
+            // the binding comes with the next key press or click
+            SettingRow::Click(_) | SettingRow::Key(_) => {
+                self.rebinding = Some(row);
+                return;
+            }
+        }
+        self.change_settings(ctx, settings);
+    }
+
+    // binds the key just pressed, with the modifiers held, to `action`.
+    // Escape cancels
+    fn rebind_key(&mut self, ctx: &mut Context, action: KeyAction, key: &str) {
+        if key == "Escape" || !input::is_key_name(key) {
+            return;
+        }
+        let mut settings = self.settings.clone();
+        settings.set_key(action, Binding::new(key, self.held.modifiers()));
+        self.change_settings(ctx, settings);
+    }
+
+    // binds the button just clicked, with the modifiers held, to `action`,
+    // or unbinds it if it already did that
+    fn rebind_button(&mut self, ctx: &mut Context, action: ClickAction, button: &str) {
+        if !input::is_button_name(button) {
+            return;
+        }
+        let mut settings = self.settings.clone();
+        settings.toggle_button(Binding::new(button, self.held.modifiers()), action);
+        self.change_settings(ctx, settings);
+    }
+
+    // what a row of the settings screen is called and shows
+    fn setting_text(&self, row: SettingRow) -> (String, String) {
+        let settings = &self.settings;
+        let (label, value) = match row {
+            SettingRow::Theme => ("THEME", settings.theme.name().to_owned()),
+            SettingRow::Difficulty => ("DEFAULT BOARD", settings.difficulty_name().to_owned()),
+            SettingRow::SafeStart => ("SAFE FIRST CLICK", settings.safe_start.name().to_owned()),
+            SettingRow::QuestionMarks => (
+                "QUESTION MARKS",
+                if settings.question_marks { "on" } else { "off" }.to_owned(),
+            ),
+            SettingRow::Chording => ("CHORDING", settings.chording.name().to_owned()),
+            SettingRow::AnimationSpeed if settings.animation_speed == 0.0 => {
+                ("ANIMATION", "off".to_owned())
+            }
+            SettingRow::AnimationSpeed => ("ANIMATION", format!("x{}", settings.animation_speed)),
+            SettingRow::Click(action) if self.rebinding == Some(row) => {
+                (action.name(), "click a button".to_owned())
+            }
+            SettingRow::Click(action) => {
+                let buttons: Vec<String> =
+                    settings.buttons(action).map(Binding::to_string).collect();
+                let value = if buttons.is_empty() {
+                    "none".to_owned()
+                } else {
+                    buttons.join(", ")
+                };
+                (action.name(), value)
+            }
+            SettingRow::Key(action) if self.rebinding == Some(row) => {
+                (action.name(), "press a key".to_owned())
+            }
+            SettingRow::Key(action) => (action.name(), settings.key(action).to_string()),
+        };
+        (label.to_uppercase(), value.to_uppercase())
+    }
+
+    fn request_hint(&mut self) {
+        if self.game_over.is_some()
+            || matches!(self.mode, Mode::Online { .. } | Mode::Watching { .. })
+        {
+            return;
+        }
+        self.assisted();
+        let hint = solver::hint(&self.board);
+        // asking again for the hint already on screen is free
+        if hint.is_some() && hint != self.hint {
+            self.hints_used += 1;
+        }
+        self.hint = hint;
+    }
+
+    // the two lines of text above the reset button
+    fn status_lines(&self) -> (String, String) {
+        if let Mode::Versus(versus) = &self.mode {
+            let detail = match versus.finish {
+                Some(Finish::Won(player)) => format!("PLAYER {} WINS", player + 1),
+                Some(Finish::Draw) => "DRAW".to_owned(),
+                None => format!("PLAYER {} TO MOVE", versus.turn + 1),
+            };
+            return (
+                format!("P1 {}  P2 {}", versus.scores[0], versus.scores[1]),
+                detail,
+            );
+        }
+        let hints = match self.mode {
+            Mode::Daily { date, scored: true } => format!("DAILY {}", date),
+            Mode::Daily { scored: false, .. } => "DAILY PRACTICE".to_owned(),
+            Mode::Puzzle { index, clean: true } => {
+                format!("P{} {}", index + 1, PUZZLES[index].goal.label())
+            }
+            Mode::Puzzle {
+                index,
+                clean: false,
+            } => format!("P{} GUESSED", index + 1),
+            Mode::Online {
+                player,
+                net_mode: NetMode::Coop,
+                ..
+            } => format!("CO-OP AS P{}", player + 1),
+            Mode::Online {
+                player,
+                winner: Some(winner),
+                ..
+            } => {
+                if winner == player {
+                    "YOU WON THE RACE".to_owned()
+                } else {
+                    format!("P{} WON THE RACE", winner + 1)
+                }
+            }
+            Mode::Online {
+                player, ref rivals, ..
+            } => match rivals
+                .iter()
+                .map(|&(_, p)| p)
+                .fold(None, |best: Option<f32>, p| {
+                    Some(best.map_or(p, |b| b.max(p)))
+                }) {
+                Some(best) => format!("RIVAL {:.0}% DONE", best * 100.0),
+                None => format!("RACE AS P{}", player + 1),
+            },
+            Mode::Watching { .. } => "WATCHING".to_owned(),
+            _ => match &self.spectators {
+                Some(spectators) => format!("LIVE, {} WATCHING", spectators.broadcaster.watchers()),
+                None => format!("HINTS USED {}", self.hints_used),
+            },
+        };
+        match self.game_over {
+            Some(GameOverState::Solved) => ("SOLVED!".to_owned(), hints),
+            Some(GameOverState::Failed { .. }) => ("RUSTED!".to_owned(), hints),
+            None => (
+                format!(
+                    "FLAGS {}/{}  {:.0}s",
+                    self.board.flags_placed(),
+                    self.total_rusts(),
+                    self.elapsed()
+                ),
+                if self.hints_used > 0 || self.mode != Mode::Classic || self.spectators.is_some() {
+                    hints
+                } else {
+                    "".to_owned()
+                },
+            ),
+        }
+    }
+
+    // the cells, plus whatever sits on top of them
+    fn draw_board(&self, ctx: &mut Context) -> GameResult<()> {
+        let over = self.game_over.map(|state| match state {
+            GameOverState::Solved => GameEvent::Solved,
+            GameOverState::Failed { x, y } => GameEvent::Failed { x, y },
+        });
+        // with the animation off the rusts are all up at once
+        let since_loss = if self.settings.animation_speed > 0.0 {
+            self.loss_elapsed
+        } else {
+            f32::INFINITY
+        };
+        let frame = frame::prepare(&self.board, over, since_loss);
+        let (cols, rows) = self.display_size();
+        for col in 0..cols {
+            for row in 0..rows {
+                let (i, j) = self.board_coords(col, row);
+                let rect = self.display_rect(col, row);
+                self.draw_tile(ctx, frame.tile(i, j), rect)?;
+                if let (None, Some(analysis)) = (over, &self.analysis) {
+                    if let Some(p) = analysis.probabilities[i][j] {
+                        self.draw_probability(ctx, p, rect)?;
+                    }
+                }
+                if self.is_ghost(col, row) {
+                    self.draw_ghost_tint(ctx, rect)?;
+                }
+            }
+        }
+        // Drawing the border of every cell
+        graphics::set_color(ctx, graphics::BLACK)?;
+        graphics::draw(ctx, &self.mesh, Point2::new(0.0, 0.0), 0.0)?;
+        self.draw_hint(ctx)?;
+        if let Mode::Watching {
+            cursor: Some((x, y)),
+            ..
+        } = self.mode
+        {
+            self.draw_cursor(ctx, x, y)?;
+        }
+        if let Mode::Versus(versus) = &self.mode {
+            self.draw_claims(ctx, versus)?;
+        } else if let (Some(GameOverState::Solved), Some(record)) = (self.game_over, &self.record) {
+            self.draw_summary(ctx, record)?;
+        }
+        Ok(())
+    }
+
+    // lifetime totals for every difficulty and a chart of recent games at
+    // the current one, drawn where the board usually is
+    fn draw_stats(&self, ctx: &mut Context) -> GameResult<()> {
+        graphics::set_color(ctx, graphics::WHITE)?;
+        let title = graphics::Text::new(ctx, "STATS", &self.font)?;
+        graphics::draw(ctx, &title, Point2::new(40.0, 25.0), 0.0)?;
+
+        let columns = [40.0, 150.0, 250.0, 340.0, 440.0, 530.0, 630.0];
+        let header = [
+            "LEVEL", "PLAYED", "WIN%", "STREAK", "BEST", "MEDIAN", "3BV/s",
+        ];
+        for (x, label) in columns.iter().zip(header.iter()) {
+            let text = graphics::Text::new(ctx, label, &self.small_font)?;
+            graphics::draw(ctx, &text, Point2::new(*x, 90.0), 0.0)?;
+        }
+        let seconds = |t: Option<f64>| t.map_or("-".to_owned(), |t| format!("{:.1}", t));
+        for (row, &(label, difficulty)) in [("EASY", EASY), ("MEDIUM", MEDIUM), ("HARD", HARD)]
+            .iter()
+            .enumerate()
+        {
+            let summary = Summary::new(&self.history, difficulty, self.variant);
+            let cells = [
+                label.to_owned(),
+                format!("{}", summary.played),
+                format!("{:.0}", summary.win_rate() * 100.0),
+                format!("{}/{}", summary.current_streak, summary.best_streak),
+                seconds(summary.best_time),
+                seconds(summary.median_time),
+                summary
+                    .average_three_bv_per_second
+                    .map_or("-".to_owned(), |s| format!("{:.2}", s)),
+            ];
+            let y = 130.0 + row as f32 * 40.0;
+            for (x, cell) in columns.iter().zip(cells.iter()) {
+                let text = graphics::Text::new(ctx, cell, &self.small_font)?;
+                graphics::draw(ctx, &text, Point2::new(*x, y), 0.0)?;
+            }
+        }
+
+        // one bar per game, as tall as it took; green won, red lost
+        let recent = stats::recent(&self.history, self.difficulty, self.variant, RECENT_GAMES);
+        let chart = graphics::Rect::new(40.0, 330.0, 640.0, 350.0);
+        let caption = graphics::Text::new(
+            ctx,
+            &format!("LAST {} GAMES, TIME PER GAME", RECENT_GAMES),
+            &self.small_font,
+        )?;
+        graphics::draw(ctx, &caption, Point2::new(chart.x, chart.y - 40.0), 0.0)?;
+        graphics::set_color(ctx, rgba(self.settings.theme.palette().panel, 255))?;
+        graphics::rectangle(ctx, graphics::DrawMode::Fill, chart)?;
+        let longest = recent.iter().map(|r| r.seconds).fold(1.0, f64::max);
+        let slot = chart.w / RECENT_GAMES as f32;
+        for (i, record) in recent.iter().enumerate() {
+            let h = (record.seconds / longest) as f32 * (chart.h - 10.0);
+            let bar = graphics::Rect::new(
+                chart.x + i as f32 * slot + 4.0,
+                chart.y + chart.h - h,
+                slot - 8.0,
+                h,
+            );
+            if record.won {
+                graphics::set_color(ctx, graphics::Color::from_rgb(60, 200, 80))?;
+            } else {
+                graphics::set_color(ctx, graphics::Color::from_rgb(200, 30, 30))?;
+            }
+            graphics::rectangle(ctx, graphics::DrawMode::Fill, bar)?;
+        }
+        graphics::set_color(ctx, graphics::WHITE)
+    }
+
+    // every setting on a row of its own, clicked to change
+    fn draw_settings(&self, ctx: &mut Context) -> GameResult<()> {
+        let palette = self.settings.theme.palette();
+        graphics::set_color(ctx, graphics::WHITE)?;
+        let title = graphics::Text::new(ctx, "SETTINGS", &self.font)?;
+        graphics::draw(ctx, &title, Point2::new(40.0, 25.0), 0.0)?;
+        for (index, row) in SettingRow::all().into_iter().enumerate() {
+            let rect = SettingRow::rect(index);
+            if self.rebinding == Some(row) {
+                graphics::set_color(ctx, rgba(palette.accent, 255))?;
+            } else {
+                graphics::set_color(ctx, rgba(palette.panel, 255))?;
+            }
+            graphics::rectangle(ctx, graphics::DrawMode::Fill, rect)?;
+            graphics::set_color(ctx, graphics::WHITE)?;
+            let (label, value) = self.setting_text(row);
+            let label = graphics::Text::new(ctx, &label, &self.small_font)?;
+            let y = self.center_text_relative_to(&label, &rect).y;
+            graphics::draw(ctx, &label, Point2::new(rect.x + 20.0, y), 0.0)?;
+            let value = graphics::Text::new(ctx, &value, &self.small_font)?;
+            let x = rect.x + rect.w - 20.0 - value.width() as f32;
+            graphics::draw(ctx, &value, Point2::new(x, y), 0.0)?;
+        }
+        Ok(())
+    }
+
+    // end of game card over the middle of the board
+    fn draw_summary(&self, ctx: &mut Context, record: &GameRecord) -> GameResult<()> {
+        let dims = DEFAULT_CELL_DIMS * DEFAULT_CELLS_ROW as f32;
+        let mut lines = vec![
+            format!("TIME {:.1}s", record.seconds),
+            format!(
+                "3BV {}   3BV/s {:.2}",
+                record.three_bv,
+                record.three_bv_per_second()
+            ),
+            format!(
+                "CLICKS {} ({}/{}/{})",
+                record.clicks.total(),
+                record.clicks.left,
+                record.clicks.right,
+                record.clicks.chord
+            ),
+            format!("EFFICIENCY {:.0}%", record.efficiency() * 100.0),
+            format!("HINTS USED {}", record.hints),
+        ];
+        match self.mode {
+            Mode::Daily { date, scored: true } => lines.push(format!("DAILY {} SHARED", date)),
+            Mode::Daily { scored: false, .. } => lines.push("DAILY PRACTICE".to_owned()),
+            Mode::Puzzle { index, clean } => lines.push(format!(
+                "{} {}",
+                PUZZLES[index].name.to_uppercase(),
+                if clean { "SOLVED" } else { "SOLVED WITH HELP" }
+            )),
+            _ => (),
+        }
+        let height = 90.0 + 45.0 * lines.len() as f32;
+        let card =
+            graphics::Rect::new(dims / 2.0 - 220.0, dims / 2.0 - height / 2.0, 440.0, height);
+        graphics::set_color(ctx, rgba(self.settings.theme.palette().panel, 230))?;
+        graphics::rectangle(ctx, graphics::DrawMode::Fill, card)?;
+        let mut line = graphics::Rect::new(card.x, card.y + 15.0, card.w, 60.0);
+        graphics::set_color(ctx, graphics::WHITE)?;
+        let title = graphics::Text::new(ctx, "SOLVED!", &self.font)?;
+        graphics::draw(
+            ctx,
+            &title,
+            self.center_text_relative_to(&title, &line),
+            0.0,
+        )?;
+        line.y += line.h;
+        line.h = 45.0;
+        for text in lines.iter() {
+            self.draw_status(ctx, text, line)?;
+            line.y += line.h;
+        }
+        Ok(())
+    }
+
+    // what went wrong, on a card over the middle of the board
+    fn draw_error(&self, ctx: &mut Context, error: &Error) -> GameResult<()> {
+        let dims = DEFAULT_CELL_DIMS * DEFAULT_CELLS_ROW as f32;
+        let mut lines = wrap(&error.to_string(), 40);
+        lines.push("".to_owned());
+        lines.push("CLICK OR PRESS A KEY".to_owned());
+        let height = 90.0 + 35.0 * lines.len() as f32;
+        let card =
+            graphics::Rect::new(dims / 2.0 - 280.0, dims / 2.0 - height / 2.0, 560.0, height);
+        graphics::set_color(ctx, rgba(self.settings.theme.palette().panel, 240))?;
+        graphics::rectangle(ctx, graphics::DrawMode::Fill, card)?;
+        graphics::set_color(ctx, graphics::Color::from_rgb(200, 30, 30))?;
+        graphics::rectangle(ctx, graphics::DrawMode::Line(4.0), card)?;
+        let mut line = graphics::Rect::new(card.x, card.y + 15.0, card.w, 60.0);
+        graphics::set_color(ctx, graphics::WHITE)?;
+        let title = graphics::Text::new(ctx, "ERROR", &self.font)?;
+        graphics::draw(
+            ctx,
+            &title,
+            self.center_text_relative_to(&title, &line),
+            0.0,
+        )?;
+        line.y += line.h;
+        line.h = 35.0;
+        for text in lines.iter() {
+            self.draw_status(ctx, text, line)?;
+            line.y += line.h;
+        }
+        Ok(())
+    }
+
+    // a passing message, on a strip across the bottom of the board
+    fn draw_notice(&self, ctx: &mut Context, notice: &str) -> GameResult<()> {
+        let dims = DEFAULT_CELL_DIMS * DEFAULT_CELLS_ROW as f32;
+        let lines = wrap(notice, 60);
+        let height = 10.0 + 30.0 * lines.len() as f32;
+        let strip = graphics::Rect::new(0.0, dims - height, dims, height);
+        graphics::set_color(ctx, rgba(self.settings.theme.palette().panel, 220))?;
+        graphics::rectangle(ctx, graphics::DrawMode::Fill, strip)?;
+        let mut line = graphics::Rect::new(strip.x, strip.y + 5.0, strip.w, 30.0);
+        for text in lines.iter() {
+            self.draw_status(ctx, text, line)?;
+            line.y += line.h;
+        }
+        Ok(())
+    }
+
+    // outlines every confirmed rust in the colour of whoever claimed it
+    fn draw_claims(&self, ctx: &mut Context, versus: &Versus) -> GameResult<()> {
+        let margin = self.margin();
+        for &(x, y, player) in &versus.claims {
+            let color = if player == 0 {
+                graphics::Color::from_rgb(70, 130, 240)
+            } else {
+                graphics::Color::from_rgb(240, 140, 40)
+            };
+            graphics::set_color(ctx, color)?;
+            let rect = self.display_rect(x + margin, y + margin);
+            graphics::rectangle(ctx, graphics::DrawMode::Line(4.0), rect)?;
+        }
+        Ok(())
+    }
+
+    // where the player we're watching has their mouse
+    fn draw_cursor(&self, ctx: &mut Context, x: f32, y: f32) -> GameResult<()> {
+        let size = DEFAULT_CELL_DIMS * self.scaling;
+        let margin = self.margin() as f32;
+        let point = Point2::new((x + margin) * size, (y + margin) * size);
+        graphics::set_color(ctx, graphics::Color::from_rgb(240, 200, 40))?;
+        graphics::circle(ctx, graphics::DrawMode::Fill, point, 6.0, 0.5)?;
+        graphics::set_color(ctx, graphics::BLACK)?;
+        graphics::circle(ctx, graphics::DrawMode::Line(2.0), point, 7.0, 0.5)
+    }
+
+    // outlines the hinted cell, green for safe, red for a rust and yellow
+    // for the best guess
+    fn draw_hint(&self, ctx: &mut Context) -> GameResult<()> {
+        let (x, y, color) = match self.hint {
+            Some(Hint::Safe { x, y }) => (x, y, graphics::Color::from_rgb(60, 200, 80)),
+            Some(Hint::Rust { x, y }) => (x, y, graphics::Color::from_rgb(220, 40, 40)),
+            Some(Hint::Guess { x, y, .. }) => (x, y, graphics::Color::from_rgb(240, 200, 40)),
+            None => return Ok(()),
+        };
+        let margin = self.margin();
+        let rect = self.display_rect(x + margin, y + margin);
+        graphics::set_color(ctx, color)?;
+        graphics::rectangle(ctx, graphics::DrawMode::Line(4.0), rect)
+    }
+
+    // number of ghost rows/columns drawn on each side of the board
+    fn margin(&self) -> usize {
+        if self.board.variant.wrapping {
+            1
+        } else {
+            0
+        }
+    }
+
+    // columns and rows drawn, ghosts included
+    fn display_size(&self) -> (usize, usize) {
+        let margin = self.margin();
+        (
+            self.board.width() + 2 * margin,
+            self.board.height() + 2 * margin,
+        )
+    }
+
+    // maps a drawn column/row back to the board cell it shows
+    fn board_coords(&self, col: usize, row: usize) -> (usize, usize) {
+        let (width, height) = (self.board.width(), self.board.height());
+        let margin = self.margin();
+        (
+            (col + width - margin) % width,
+            (row + height - margin) % height,
+        )
+    }
+
+    fn is_ghost(&self, col: usize, row: usize) -> bool {
+        let margin = self.margin();
+        col < margin
+            || row < margin
+            || col >= self.board.width() + margin
+            || row >= self.board.height() + margin
+    }
+
+    fn display_rect(&self, col: usize, row: usize) -> graphics::Rect {
+        let size = DEFAULT_CELL_DIMS * self.scaling;
+        graphics::Rect::new(col as f32 * size, row as f32 * size, size, size)
+    }
+
+    fn draw_button(
+        &self,
+        ctx: &mut Context,
+        label: &str,
+        rect: graphics::Rect,
+        font: &graphics::Font,
+        active: bool,
+    ) -> GameResult<()> {
+        let palette = self.settings.theme.palette();
+        if active {
+            graphics::set_color(ctx, rgba(palette.accent, 255))?;
+        } else {
+            graphics::set_color(ctx, rgba(palette.panel, 255))?;
+        }
+        graphics::rectangle(ctx, graphics::DrawMode::Fill, rect)?;
+        graphics::set_color(ctx, graphics::WHITE)?;
+        let text = graphics::Text::new(ctx, label, font)?;
+        graphics::draw(ctx, &text, self.center_text_relative_to(&text, &rect), 0.0)
+    }
+
+    // plain panel text, no button behind it
+    fn draw_status(&self, ctx: &mut Context, label: &str, rect: graphics::Rect) -> GameResult<()> {
+        graphics::set_color(ctx, graphics::WHITE)?;
+        let text = graphics::Text::new(ctx, label, &self.small_font)?;
+        graphics::draw(ctx, &text, self.center_text_relative_to(&text, &rect), 0.0)
+    }
+
+    fn draw_number(&self, ctx: &mut Context, count: u8, rect: graphics::Rect) -> GameResult<()> {
+        let shown_num = if count == 0 {
+            "".to_owned()
+        } else {
+            format!("{}", count)
+        };
+        self.draw_centered(ctx, &shown_num, rect)
+    }
+
+    // marks images of stacked flags or rusts with how many there are
+    fn draw_stack_count(
+        &self,
+        ctx: &mut Context,
+        count: u8,
+        rect: graphics::Rect,
+    ) -> GameResult<()> {
+        if count < 2 {
+            return Ok(());
+        }
+        graphics::set_color(ctx, rgba(self.settings.theme.palette().accent, 255))?;
+        self.draw_centered(ctx, &format!("x{}", count), rect)?;
+        graphics::set_color(ctx, graphics::WHITE)
+    }
+
+    fn draw_centered(
+        &self,
+        ctx: &mut Context,
+        shown: &str,
+        rect: graphics::Rect,
+    ) -> GameResult<()> {
+        let text = graphics::Text::new(ctx, shown, &self.num_font)?;
+        graphics::draw_ex(
+            ctx,
+            &text,
+            DrawParam {
+                dest: self.center_text_relative_to(&text, &rect),
+                ..Default::default()
+            },
+        )
+    }
+
+    // one cell as `frame` worked it out. After a loss the fatal rust is
+    // highlighted and flags on the wrong cells get crossed out
+    fn draw_tile(&self, ctx: &mut Context, tile: Tile, rect: graphics::Rect) -> GameResult<()> {
+        let scale = Point2::new(self.scaling, self.scaling);
+        let dest = Point2::new(rect.x, rect.y);
+        graphics::set_color(ctx, graphics::WHITE)?;
+        match tile {
+            Tile::Covered => {
+                let cover = graphics::Rect::new(rect.x, rect.y, rect.w - 2.0, rect.h - 2.0);
+                graphics::rectangle(ctx, graphics::DrawMode::Fill, cover)
+            }
+            Tile::Marked => {
+                let cover = graphics::Rect::new(rect.x, rect.y, rect.w - 2.0, rect.h - 2.0);
+                graphics::rectangle(ctx, graphics::DrawMode::Fill, cover)?;
+                graphics::set_color(ctx, rgba(self.settings.theme.palette().accent, 255))?;
+                self.draw_centered(ctx, "?", rect)?;
+                graphics::set_color(ctx, graphics::WHITE)
+            }
+            Tile::Flagged { flags, wrong } => {
+                graphics::draw_ex(
+                    ctx,
+                    &self.flag,
+                    DrawParam {
+                        dest,
+                        scale,
+                        ..Default::default()
+                    },
+                )?;
+                self.draw_stack_count(ctx, flags, rect)?;
+                if wrong {
+                    self.draw_cross(ctx, rect)?;
+                }
+                Ok(())
+            }
+            Tile::Number(count) => self.draw_number(ctx, count, rect),
+            Tile::Rust {
+                rusts,
+                pop,
+                fatal,
+                won,
+            } => {
+                if fatal {
+                    graphics::set_color(ctx, graphics::Color::from_rgb(200, 30, 30))?;
+                    graphics::rectangle(ctx, graphics::DrawMode::Fill, rect)?;
+                    graphics::set_color(ctx, graphics::WHITE)?;
+                }
+                if pop <= 0.0 {
+                    return Ok(());
+                }
+                let image = if won { &self.happy_image } else { &self.image };
+                // grow from the middle of the cell
+                let offset = rect.w * (1.0 - pop) / 2.0;
+                graphics::draw_ex(
+                    ctx,
+                    image,
+                    DrawParam {
+                        dest: Point2::new(rect.x + offset, rect.y + offset),
+                        scale: Point2::new(self.scaling * pop, self.scaling * pop),
+                        ..Default::default()
+                    },
+                )?;
+                self.draw_stack_count(ctx, rusts, rect)
+            }
+        }
+    }
+
+    // marks a misplaced flag
+    fn draw_cross(&self, ctx: &mut Context, rect: graphics::Rect) -> GameResult<()> {
+        let inset = rect.w * 0.15;
+        let (left, right) = (rect.x + inset, rect.x + rect.w - inset);
+        let (top, bottom) = (rect.y + inset, rect.y + rect.h - inset);
+        let width = (6.0 * self.scaling).max(2.0);
+        graphics::set_color(ctx, graphics::Color::from_rgb(200, 30, 30))?;
+        graphics::line(
+            ctx,
+            &[Point2::new(left, top), Point2::new(right, bottom)],
+            width,
+        )?;
+        graphics::line(
+            ctx,
+            &[Point2::new(right, top), Point2::new(left, bottom)],
+            width,
+        )?;
+        graphics::set_color(ctx, graphics::WHITE)
+    }
+
+    // heatmap square over a hidden cell, green when it is surely safe
+    // through yellow to red when it surely holds a rust
+    fn draw_probability(&self, ctx: &mut Context, p: f32, rect: graphics::Rect) -> GameResult<()> {
+        let (r, g) = if p < 0.5 {
+            (p * 2.0, 1.0)
+        } else {
+            (1.0, (1.0 - p) * 2.0)
+        };
+        graphics::set_color(ctx, graphics::Color::new(r, g, 0.0, 0.55))?;
+        graphics::rectangle(ctx, graphics::DrawMode::Fill, rect)?;
+        // percentages only fit on reasonably big cells
+        if rect.w >= 50.0 {
+            graphics::set_color(ctx, graphics::BLACK)?;
+            let text = graphics::Text::new(ctx, &format!("{:.0}%", p * 100.0), &self.small_font)?;
+            graphics::draw(ctx, &text, self.center_text_relative_to(&text, &rect), 0.0)?;
+        }
+        graphics::set_color(ctx, graphics::WHITE)
+    }
+
+    // dims the ghost copies of wrapped cells so they read as a preview
+    fn draw_ghost_tint(&self, ctx: &mut Context, rect: graphics::Rect) -> GameResult<()> {
+        graphics::set_color(ctx, rgba(self.settings.theme.palette().background, 160))?;
+        graphics::rectangle(ctx, graphics::DrawMode::Fill, rect)
+    }
+
     fn center_text_relative_to(&self, text: &graphics::Text, rect: &graphics::Rect) -> Point2 {
         let f_w = text.width() as f32;
         let f_h = text.height() as f32;
@@ -158,279 +1573,444 @@ impl MainState {
 // The `EventHandler` trait also contains callbacks for event handling
 // that you can override if you wish, but the defaults are fine.
 impl event::EventHandler for MainState {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if let Mode::Online { .. } | Mode::Watching { .. } = self.mode {
+            let messages = self.net.as_ref().map_or_else(Vec::new, Client::poll);
+            for message in messages {
+                let result = self.on_server_message(ctx, message);
+                self.report_failure(result);
+            }
+        } else {
+            // leaving online play hangs up
+            self.net = None;
+        }
+        let online = matches!(self.mode, Mode::Online { .. } | Mode::Watching { .. });
+        if self.analysis_mode && self.analysis.is_none() && self.game_over.is_none() && !online {
+            self.analysis = Some(solver::analyze(&self.board));
+        }
+        // drives the rusts popping up after a loss
+        if let Some(GameOverState::Failed { .. }) = self.game_over {
+            self.loss_elapsed += timer::duration_to_f64(timer::get_delta(ctx)) as f32
+                * self.settings.animation_speed;
+        }
+        self.poll_settings(ctx);
+        self.publish();
         Ok(())
     }
 
+    fn key_down_event(&mut self, ctx: &mut Context, keycode: Keycode, _keymod: Mod, _repeat: bool) {
+        let key = format!("{:?}", keycode);
+        if self.held.track(&key, true) {
+            return;
+        }
+        // the first key after an error just clears it
+        if self.error.take().is_some() {
+            return;
+        }
+        // a key pressed while a click row waits for its button cancels it
+        if let Some(row) = self.rebinding.take() {
+            if let SettingRow::Key(action) = row {
+                self.rebind_key(ctx, action, &key);
+            }
+            return;
+        }
+        match self.settings.key_action(&key, self.held.modifiers()) {
+            Some(KeyAction::Hint) => self.request_hint(),
+            Some(KeyAction::Analysis) => {
+                self.analysis_mode = !self.analysis_mode;
+                self.assisted();
+            }
+            Some(KeyAction::Export) => self.export(),
+            Some(KeyAction::Spectate) => self.toggle_spectators(),
+            Some(KeyAction::Settings) => self.toggle_settings(),
+            // boards dropped into the data directory as board.txt
+            Some(KeyAction::Load) => match storage::data_file("board.txt") {
+                Ok(path) => {
+                    let result = self.load(ctx, &path);
+                    self.report_failure(result);
+                }
+                Err(e) => self.report(Error::save("find board.txt", e)),
+            },
+            None => (),
+        }
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: Keycode, _keymod: Mod, _repeat: bool) {
+        self.held.track(&format!("{:?}", keycode), false);
+    }
+
+    // modifiers let go of in another window would otherwise stay held
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) {
+        if !gained {
+            self.held = HeldKeys::default();
+        }
+    }
+
     fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: i32, y: i32) {
+        // as does the first click
+        if self.error.take().is_some() {
+            return;
+        }
         let click_point = Point2::new(x as f32, y as f32);
-        let cell_x = (x as f32 / (DEFAULT_CELL_DIMS * self.scaling)) as usize;
-        let cell_y = (y as f32 / (DEFAULT_CELL_DIMS * self.scaling)) as usize;
-        if cell_x > (self.difficulty.1 - 1) as usize {
+        // the settings screen takes everything left of the panel, whatever
+        // the size of the board behind it
+        if self.screen == Screen::Settings
+            && click_point.x < DEFAULT_CELL_DIMS * DEFAULT_CELLS_ROW as f32
+        {
+            if let Some(SettingRow::Click(action)) = self.rebinding.take() {
+                self.rebind_button(ctx, action, &format!("{:?}", button));
+                return;
+            }
+            let clicked = SettingRow::all()
+                .into_iter()
+                .enumerate()
+                .find(|&(index, _)| SettingRow::rect(index).contains(click_point));
+            if let Some((_, row)) = clicked {
+                self.change_setting(ctx, row);
+            }
+            return;
+        }
+        let col = (x as f32 / (DEFAULT_CELL_DIMS * self.scaling)) as usize;
+        let row = (y as f32 / (DEFAULT_CELL_DIMS * self.scaling)) as usize;
+        let (cols, rows) = self.display_size();
+        if col >= cols || row >= rows {
+            let mut result = Ok(());
             if self.reset_button.contains(click_point) {
-                self.reset(ctx, self.difficulty).expect("poop");
+                result = match self.mode.clone() {
+                    Mode::Classic => self.reset(ctx, self.difficulty),
+                    Mode::Daily { .. } => self.start_daily(ctx),
+                    Mode::File(path) => self.load(ctx, &path),
+                    Mode::Puzzle { index, .. } => self.start_puzzle(ctx, index),
+                    Mode::Versus(versus) => self.start_versus(ctx, versus.scoring),
+                    Mode::Online { .. } => match &mut self.net {
+                        Some(client) => client
+                            .send(&ClientMessage::NewGame)
+                            .map_err(|e| Error::network("the server", e)),
+                        None => Ok(()),
+                    },
+                    // the game being watched is theirs to reset
+                    Mode::Watching { .. } => Ok(()),
+                };
             }
             if self.easy_button.contains(click_point) {
-                self.reset(ctx, EASY).expect("poop");
+                result = self.reset(ctx, EASY);
             }
             if self.medium_button.contains(click_point) {
-                self.reset(ctx, MEDIUM).expect("poop");
+                result = self.reset(ctx, MEDIUM);
             }
             if self.hard_button.contains(click_point) {
-                self.reset(ctx, HARD).expect("poop");
+                result = self.reset(ctx, HARD);
+            }
+            if self.wrap_button.contains(click_point) {
+                self.variant.wrapping = !self.variant.wrapping;
+                result = self.reset(ctx, self.difficulty);
+            }
+            if self.neighborhood_button.contains(click_point) {
+                self.variant.neighborhood = self.variant.neighborhood.next();
+                result = self.reset(ctx, self.difficulty);
+            }
+            if self.rusts_button.contains(click_point) {
+                self.variant.max_rusts = self.variant.max_rusts % 3 + 1;
+                result = self.reset(ctx, self.difficulty);
+            }
+            if self.hint_button.contains(click_point) {
+                self.request_hint();
+            }
+            if self.analysis_button.contains(click_point) {
+                self.analysis_mode = !self.analysis_mode;
+                self.assisted();
+            }
+            if self.stats_button.contains(click_point) {
+                self.toggle_stats();
+            }
+            if self.daily_button.contains(click_point) {
+                result = self.start_daily(ctx);
+            }
+            if self.export_button.contains(click_point) {
+                self.export();
+            }
+            if self.versus_button.contains(click_point) {
+                let scoring = match &self.mode {
+                    Mode::Versus(versus) if versus.scoring == Scoring::Reveals => Scoring::Flags,
+                    _ => Scoring::Reveals,
+                };
+                result = self.start_versus(ctx, scoring);
             }
+            if self.puzzle_button.contains(click_point) {
+                let next = self.next_puzzle();
+                result = self.start_puzzle(ctx, next);
+            }
+            self.report_failure(result);
+            return;
+        }
+        if self.screen != Screen::Game
+            || self.game_over.is_some()
+            || matches!(self.mode, Mode::Watching { .. })
+        {
             return;
         }
-        match button {
-            MouseButton::Right => {
-                if self.board.cells[cell_x][cell_y].is_hidden {
-                    self.board.cells[cell_x][cell_y].is_flagged =
-                        !self.board.cells[cell_x][cell_y].is_flagged
+        // clicks on a ghost cell act on the cell it mirrors
+        let (cell_x, cell_y) = self.board_coords(col, row);
+        let chord = Move::Chord {
+            x: cell_x,
+            y: cell_y,
+        };
+        let action = match self
+            .settings
+            .click_action(&format!("{:?}", button), self.held.modifiers())
+        {
+            Some(action) => action,
+            None => return,
+        };
+        let mv = match (action, self.settings.chording) {
+            (ClickAction::Flag, _) => Move::Flag {
+                x: cell_x,
+                y: cell_y,
+            },
+            (ClickAction::Chord, Chording::Off) => return,
+            (ClickAction::Chord, _) => chord,
+            // revealing a number that is already showing chords it
+            (ClickAction::Reveal, Chording::Click)
+                if !self.board.cell(cell_x, cell_y).is_hidden() =>
+            {
+                chord
+            }
+            (ClickAction::Reveal, _) => Move::Reveal {
+                x: cell_x,
+                y: cell_y,
+            },
+        };
+        self.hint = None;
+        self.analysis = None;
+        // online moves go to the server, and come back as what they uncovered
+        if let (Mode::Online { .. }, Some(client)) = (&self.mode, &mut self.net) {
+            if let Err(e) = client.send(&ClientMessage::Move(mv)) {
+                self.report(Error::network("the server", e));
+            }
+            match mv {
+                Move::Reveal { .. } => self.clicks.left += 1,
+                Move::Flag { .. } => self.clicks.right += 1,
+                Move::Chord { .. } => self.clicks.chord += 1,
+            }
+            if self.started_at.is_none() {
+                self.started_at = Some(Instant::now());
+            }
+            return;
+        }
+        if let Mode::Puzzle { ref mut clean, .. } = self.mode {
+            if self.analysis_mode || !puzzle::is_deduced(&self.board, mv) {
+                *clean = false;
+            }
+        }
+        if let Mode::Versus(ref mut versus) = self.mode {
+            // the first reveal is made safe without rolling a new board,
+            // which would end the versus game
+            if let (true, Move::Reveal { .. }) = (self.first_click, mv) {
+                self.board.clear_rusts_at(cell_x, cell_y);
+                self.three_bv = self.board.three_bv();
+            }
+            let finish = versus.play(&mut self.board, mv);
+            self.first_click &= self.board.revealed_safe() == 0 && versus.claims.is_empty();
+            if self.started_at.is_none() {
+                self.started_at = Some(Instant::now());
+            }
+            if finish.is_some() {
+                self.on_game_event(self.board.outcome.unwrap_or(GameEvent::Solved));
+            }
+            return;
+        }
+        let mut event = match mv {
+            Move::Flag { .. } => {
+                // a question mark left over from before they were turned
+                // off still comes off with a click
+                if self.settings.question_marks || self.board.cell(cell_x, cell_y).is_marked() {
+                    self.board.cycle_mark(cell_x, cell_y);
+                } else {
+                    self.board.cycle_flag(cell_x, cell_y);
                 }
+                self.clicks.right += 1;
+                None
+            }
+            Move::Chord { .. } => {
+                self.clicks.chord += 1;
+                self.board.chord(cell_x, cell_y)
             }
-            _ => {
+            Move::Reveal { .. } => {
                 // user should never encounter a bomb on the first click
-                if self.first_click {
-                    if self.board.cells[cell_x][cell_y].is_rust {
-                        let mut clicked_bomb = true;
-                        while clicked_bomb {
-                            self.reset(ctx, self.difficulty).expect("poop");
-                            if !self.board.cells[cell_x][cell_y].is_rust {
-                                clicked_bomb = false;
-                                self.first_click = false;
+                if let Mode::Daily { date, scored } = self.mode {
+                    if self.first_click {
+                        // rolling a new board would break the shared daily one
+                        self.board.clear_rusts_at(cell_x, cell_y);
+                        self.three_bv = self.board.three_bv();
+                        self.first_click = false;
+                        // the attempt counts from the first click, even if
+                        // it is never finished
+                        if scored {
+                            if let Err(e) = daily::record(&DailyResult::started(date)) {
+                                self.report(Error::save("save the daily result", e));
                             }
                         }
                     }
-                    self.first_click = false;
-                }
-                // ignore clicks on flagged cells
-                if self.board.cells[cell_x][cell_y].is_flagged {
-                    return;
                 }
-                // if count is 0, trigger flood fill following rules
-                if self.board.cells[cell_x][cell_y].rust_count == 0
-                    && !self.board.cells[cell_x][cell_y].is_rust
-                {
-                    self.board.flood_fill(cell_x, cell_y);
-                // if this is a bomb, trigger flood fill ignoring rules (true flood fill)
-                } else {
-                    self.board.cells[cell_x][cell_y].is_hidden = false;
+                if self.first_click && self.board.cell(cell_x, cell_y).is_rust() {
+                    match self.settings.safe_start {
+                        SafeStart::Reroll => {
+                            let mut clicked_bomb = true;
+                            while clicked_bomb {
+                                if let Err(e) = self.reset(ctx, self.difficulty) {
+                                    self.report(e);
+                                    return;
+                                }
+                                if !self.board.cell(cell_x, cell_y).is_rust() {
+                                    clicked_bomb = false;
+                                }
+                            }
+                        }
+                        SafeStart::Move => {
+                            self.board.clear_rusts_at(cell_x, cell_y);
+                            self.three_bv = self.board.three_bv();
+                        }
+                        SafeStart::Off => (),
+                    }
                 }
+                self.first_click = false;
+                self.clicks.left += 1;
+                self.board.reveal(cell_x, cell_y)
             }
+        };
+        if self.started_at.is_none() {
+            self.started_at = Some(Instant::now());
+        }
+        // finding every rust ends a puzzle without the board being cleared
+        if let Mode::Puzzle { index, .. } = self.mode {
+            if event.is_none() && PUZZLES[index].is_complete(&self.board) {
+                event = Some(GameEvent::Solved);
+            }
+        }
+        if let Some(event) = event {
+            self.on_game_event(event);
+        }
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        _state: MouseState,
+        x: i32,
+        y: i32,
+        _xrel: i32,
+        _yrel: i32,
+    ) {
+        let size = DEFAULT_CELL_DIMS * self.scaling;
+        let margin = self.margin() as f32;
+        let (x, y) = (x as f32 / size - margin, y as f32 / size - margin);
+        let (width, height) = (self.board.width() as f32, self.board.height() as f32);
+        // off the board the cursor stays where it left
+        if x >= 0.0 && y >= 0.0 && x < width && y < height {
+            self.cursor = Some((x, y));
         }
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         // settings panel
-        let scaling = DEFAULT_CELLS_ROW as f32 / self.difficulty.1 as f32;
-        let scale = Point2::new(scaling, scaling);
         graphics::clear(ctx);
-        graphics::set_background_color(ctx, graphics::Color::from_rgb(75, 27, 34));
+        let palette = self.settings.theme.palette();
+        graphics::set_background_color(ctx, rgba(palette.background, 255));
         let dims = DEFAULT_CELL_DIMS * DEFAULT_CELLS_ROW as f32;
-        graphics::set_color(ctx, graphics::Color::from_rgb(21, 4, 12))?;
+        graphics::set_color(ctx, rgba(palette.panel, 255))?;
         // dividing line
         graphics::line(
             ctx,
             &[Point2::new(dims + 5.0, 0.0), Point2::new(dims + 5.0, dims)],
             10.0,
         )?;
-        // reset button
-        graphics::rectangle(ctx, graphics::DrawMode::Fill, self.reset_button)?;
-        graphics::set_color(ctx, graphics::WHITE)?;
-        let reset = graphics::Text::new(ctx, &"RESET", &self.font)?;
-        graphics::draw(
+        self.draw_button(ctx, "RESET", self.reset_button, &self.font, false)?;
+        self.draw_button(ctx, "EASY", self.easy_button, &self.font, false)?;
+        self.draw_button(ctx, "MEDIUM", self.medium_button, &self.font, false)?;
+        self.draw_button(ctx, "HARD", self.hard_button, &self.font, false)?;
+        self.draw_button(
             ctx,
-            &reset,
-            self.center_text_relative_to(&reset, &self.reset_button),
-            0.0,
+            "WRAP",
+            self.wrap_button,
+            &self.small_font,
+            self.variant.wrapping,
         )?;
-        //easy button
-        graphics::set_color(ctx, graphics::Color::from_rgb(21, 4, 12))?;
-        graphics::rectangle(ctx, graphics::DrawMode::Fill, self.easy_button)?;
-        graphics::set_color(ctx, graphics::WHITE)?;
-        let easy = graphics::Text::new(ctx, &"EASY", &self.font)?;
-        graphics::draw(
+        self.draw_button(
             ctx,
-            &easy,
-            self.center_text_relative_to(&easy, &self.easy_button),
-            0.0,
+            self.variant.neighborhood.label(),
+            self.neighborhood_button,
+            &self.small_font,
+            self.variant.neighborhood != Neighborhood::Moore,
         )?;
-
-        // medium button
-        graphics::set_color(ctx, graphics::Color::from_rgb(21, 4, 12))?;
-        graphics::rectangle(ctx, graphics::DrawMode::Fill, self.medium_button)?;
-        let medium = graphics::Text::new(ctx, &"MEDIUM", &self.font)?;
-        graphics::set_color(ctx, graphics::WHITE)?;
-        graphics::draw(
+        self.draw_button(
             ctx,
-            &medium,
-            self.center_text_relative_to(&medium, &self.medium_button),
-            0.0,
+            &format!("RUSTS x{}", self.variant.max_rusts),
+            self.rusts_button,
+            &self.small_font,
+            self.variant.max_rusts > 1,
         )?;
-        // hard button
-        graphics::set_color(ctx, graphics::Color::from_rgb(21, 4, 12))?;
-        graphics::rectangle(ctx, graphics::DrawMode::Fill, self.hard_button)?;
-        graphics::set_color(ctx, graphics::WHITE)?;
-        let hard = graphics::Text::new(ctx, &"HARD", &self.font)?;
-        graphics::set_color(ctx, graphics::WHITE)?;
-        graphics::draw(
+        self.draw_button(ctx, "HINT", self.hint_button, &self.small_font, false)?;
+        self.draw_button(
             ctx,
-            &hard,
-            self.center_text_relative_to(&hard, &self.hard_button),
-            0.0,
+            "ANALYSIS",
+            self.analysis_button,
+            &self.small_font,
+            self.analysis_mode,
+        )?;
+        self.draw_button(
+            ctx,
+            "STATS",
+            self.stats_button,
+            &self.small_font,
+            self.screen == Screen::Stats,
+        )?;
+        self.draw_button(
+            ctx,
+            "DAILY",
+            self.daily_button,
+            &self.small_font,
+            matches!(self.mode, Mode::Daily { .. }),
+        )?;
+        self.draw_button(
+            ctx,
+            "PUZZLE",
+            self.puzzle_button,
+            &self.small_font,
+            matches!(self.mode, Mode::Puzzle { .. }),
         )?;
+        let (versus_label, versus_active) = match &self.mode {
+            Mode::Versus(versus) => (versus.scoring.label(), true),
+            _ => ("VERSUS", false),
+        };
+        self.draw_button(
+            ctx,
+            versus_label,
+            self.versus_button,
+            &self.small_font,
+            versus_active,
+        )?;
+        self.draw_button(ctx, "EXPORT", self.export_button, &self.small_font, false)?;
+        let (status, detail) = self.status_lines();
+        let mut line = self.status_rect;
+        line.h /= 2.0;
+        self.draw_status(ctx, &status, line)?;
+        line.y += line.h;
+        self.draw_status(ctx, &detail, line)?;
         // end settings panel
 
-        match &self.game_over {
-            Some(state) => {
-                let cell_image = match state {
-                    GameOverState::Solved => &self.happy_image,
-                    GameOverState::Failed => &self.image,
-                };
-                graphics::set_color(ctx, graphics::BLACK)?;
-                graphics::draw(ctx, &self.mesh, Point2::new(0.0, 0.0), 0.0).unwrap();
-                graphics::set_color(ctx, graphics::WHITE)?;
-
-                for i in 0..self.difficulty.1 {
-                    for j in 0..self.difficulty.1 {
-                        let cell = &self.board.cells[i][j];
-
-                        if self.board.cells[i][j].is_rust {
-                            let dest = graphics::Point2::new(
-                                cell.position.x * DEFAULT_CELL_DIMS * scale.x,
-                                cell.position.y * DEFAULT_CELL_DIMS * scale.y,
-                            );
-                            graphics::draw_ex(
-                                ctx,
-                                cell_image,
-                                DrawParam {
-                                    dest,
-                                    scale,
-                                    ..Default::default()
-                                },
-                            )?;
-                        } else {
-                            let shown_num = if cell.rust_count == 0 {
-                                "".to_owned()
-                            } else {
-                                format!("{}", cell.rust_count)
-                            };
-                            let text = graphics::Text::new(ctx, &shown_num, &self.num_font)?;
-                            graphics::draw_ex(
-                                ctx,
-                                &text,
-                                DrawParam {
-                                    dest: self.center_text_relative_to(
-                                        &text,
-                                        &graphics::Rect::new(
-                                            cell.position.x * DEFAULT_CELL_DIMS * scale.x,
-                                            cell.position.y * DEFAULT_CELL_DIMS * scale.y,
-                                            DEFAULT_CELL_DIMS * scale.x,
-                                            DEFAULT_CELL_DIMS * scale.y,
-                                        ),
-                                    ),
-                                    ..Default::default()
-                                },
-                            )?;
-                        }
-                    }
-                }
-            }
-            _ => {
-                let mut correct = 0;
-                for i in 0..self.difficulty.1 as usize {
-                    for j in 0..self.difficulty.1 as usize {
-                        let cell = &self.board.cells[i][j];
-                        if cell.game_over {
-                            self.game_over = Some(GameOverState::Failed)
-                        }
-                        if !cell.is_hidden && !cell.is_rust {
-                            correct += 1;
-                        }
-                        if !cell.is_hidden && cell.is_rust {
-                            self.game_over = Some(GameOverState::Failed);
-                            correct = 0;
-                        }
-                        if correct == self.difficulty.1.pow(2) - self.difficulty.0 {
-                            self.game_over = Some(GameOverState::Solved);
-                        }
-                        if cell.is_flagged {
-                            let dest_point = graphics::Point2::new(
-                                cell.position.x * DEFAULT_CELL_DIMS * scale.x,
-                                cell.position.y * DEFAULT_CELL_DIMS * scale.y,
-                            );
-                            graphics::draw_ex(
-                                ctx,
-                                &self.flag,
-                                graphics::DrawParam {
-                                    dest: dest_point,
-                                    scale,
-                                    ..Default::default()
-                                },
-                            )?;
-                        } else if !cell.is_hidden && cell.is_rust {
-                            let dest_point = graphics::Point2::new(
-                                cell.position.x * DEFAULT_CELL_DIMS * scale.x,
-                                cell.position.y * DEFAULT_CELL_DIMS * scale.y,
-                            );
-                            graphics::draw_ex(
-                                ctx,
-                                &self.image,
-                                graphics::DrawParam {
-                                    dest: dest_point,
-                                    scale,
-                                    ..Default::default()
-                                },
-                            )?;
-                        }
-                        // Drawing the border of every cell
-                        graphics::set_color(ctx, graphics::BLACK)?;
-                        graphics::draw(ctx, &self.mesh, Point2::new(0.0, 0.0), 0.0).unwrap();
-
-                        graphics::set_color(ctx, graphics::WHITE)?;
-                        if !cell.is_rust && (!cell.is_flagged || !cell.is_hidden) {
-                            // self.draw_text(ctx);
-                            let cell = &self.board.cells[i][j];
-                            let shown_num = if cell.rust_count == 0 {
-                                "".to_owned()
-                            } else {
-                                format!("{}", cell.rust_count)
-                            };
-                            let text = graphics::Text::new(ctx, &shown_num, &self.num_font)?;
-                            graphics::draw_ex(
-                                ctx,
-                                &text,
-                                DrawParam {
-                                    dest: self.center_text_relative_to(
-                                        &text,
-                                        &graphics::Rect::new(
-                                            cell.position.x * DEFAULT_CELL_DIMS * scale.x,
-                                            cell.position.y * DEFAULT_CELL_DIMS * scale.y,
-                                            DEFAULT_CELL_DIMS * scale.x,
-                                            DEFAULT_CELL_DIMS * scale.y,
-                                        ),
-                                    ),
-                                    ..Default::default()
-                                },
-                            )?;
-                        }
-
-                        graphics::set_color(ctx, graphics::WHITE)?;
-                        // drawing the cell cover if the cell is hidden
-                        if cell.is_hidden && !cell.is_flagged {
-                            let mut rect = graphics::Rect::new(
-                                cell.position.x * DEFAULT_CELL_DIMS * scale.x,
-                                cell.position.y * DEFAULT_CELL_DIMS * scale.y,
-                                DEFAULT_CELL_DIMS - (2.0 / scale.x),
-                                DEFAULT_CELL_DIMS - (2.0 / scale.y),
-                            );
-                            rect.scale(scale.x, scale.y);
-                            graphics::rectangle(ctx, graphics::DrawMode::Fill, rect)?;
-                        }
-                    }
-                }
+        match self.screen {
+            Screen::Game => self.draw_board(ctx)?,
+            Screen::Stats => self.draw_stats(ctx)?,
+            Screen::Settings => self.draw_settings(ctx)?,
+        }
+        if let Some((notice, at)) = &self.notice {
+            if at.elapsed() < NOTICE_TIME {
+                self.draw_notice(ctx, notice)?;
             }
         }
+        if let Some(error) = &self.error {
+            self.draw_error(ctx, error)?;
+        }
         graphics::present(ctx);
 
         // self.frames += 1;
@@ -443,14 +2023,20 @@ impl event::EventHandler for MainState {
 }
 
 pub fn main() {
-    let ctx = &mut ContextBuilder::new("Rust Sweeper", "ggez")
+    let built = ContextBuilder::new("Rust Sweeper", "ggez")
         .window_setup(WindowSetup::default().title("Rust Sweeper "))
         .window_mode(WindowMode::default().dimensions(
             (DEFAULT_CELL_DIMS * DEFAULT_CELLS_ROW as f32 + CTRL_PANEL_WIDTH) as u32,
             (DEFAULT_CELL_DIMS * DEFAULT_CELLS_ROW as f32) as u32,
         ))
-        .build()
-        .unwrap();
+        .build();
+    let ctx = &mut match built {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            println!("Could not open the window: {}", e);
+            process::exit(1);
+        }
+    };
 
     // We add the CARGO_MANIFEST_DIR/resources to the filesystem's path
     // so that ggez will look in our cargo project directory for files.
@@ -460,7 +2046,46 @@ pub fn main() {
         ctx.filesystem.mount(&path, true);
     }
 
-    let state = &mut MainState::new(ctx).unwrap();
+    let state = &mut match MainState::new(ctx) {
+        Ok(state) => state,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+    // `rustsweeper path/to/board.txt` starts on a board from a file,
+    // `rustsweeper --connect host:port [--name N]` joins a server and
+    // `rustsweeper --watch [host:port]` follows a game streamed with S
+    // (without an address, even when `--name` follows, they use the default)
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("--connect") => {
+            let addr = args
+                .get(1)
+                .filter(|arg| !arg.starts_with("--"))
+                .cloned()
+                .unwrap_or_else(|| format!("127.0.0.1:{}", net::DEFAULT_PORT));
+            let name = args
+                .iter()
+                .position(|arg| arg == "--name")
+                .and_then(|i| args.get(i + 1))
+                .map_or("player", String::as_str);
+            state.connect(&addr, name);
+        }
+        Some("--watch") => {
+            let addr = args
+                .get(1)
+                .filter(|arg| !arg.starts_with("--"))
+                .cloned()
+                .unwrap_or_else(|| format!("127.0.0.1:{}", net::SPECTATE_PORT));
+            state.watch(&addr);
+        }
+        Some(file) => {
+            let result = state.load(ctx, path::Path::new(file));
+            state.report_failure(result);
+        }
+        None => (),
+    }
     if let Err(e) = event::run(ctx, state) {
         println!("Error encountered: {}", e);
     } else {
@@ -0,0 +1,566 @@
+//! The line based protocol spoken between the multiplayer server
+//! (`src/bin/server.rs`) and the game. Every message is one line of space
+//! separated words, so a session can be poked at with `nc` by hand.
+//!
+//! The server owns the boards and only ever tells clients what a player
+//! could see: uncovered cells with their counts and flags, and where the
+//! rusts were once a board is over.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+use std::time::Duration;
+
+use crate::board::{Board, GameEvent};
+use crate::cell::Cell;
+use crate::player::Move;
+use crate::variant::{Neighborhood, Variant};
+
+pub const DEFAULT_PORT: u16 = 7878;
+/// Where a game streams itself to spectators.
+pub const SPECTATE_PORT: u16 = 7879;
+
+// the most rusts any variant lets a cell hold, and so the highest count a
+// cell can show with all 24 neighbors of `radius2` full
+const MAX_STACK: u8 = 9;
+const MAX_COUNT: u8 = 24 * MAX_STACK;
+
+/// How players share a server.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetMode {
+    /// Everyone plays the same board together.
+    Coop,
+    /// Everyone gets their own copy of the same board, first to clear it wins.
+    Race,
+}
+
+impl NetMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "coop" => Some(NetMode::Coop),
+            "race" => Some(NetMode::Race),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NetMode::Coop => "coop",
+            NetMode::Race => "race",
+        }
+    }
+}
+
+/// What a client can ask of the server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientMessage {
+    Hello {
+        name: String,
+    },
+    Move(Move),
+    /// A new shared board in co-op, a new round for everyone in a race.
+    NewGame,
+}
+
+impl ClientMessage {
+    pub fn to_line(&self) -> String {
+        match self {
+            ClientMessage::Hello { name } => format!("HELLO {}", name),
+            ClientMessage::Move(Move::Reveal { x, y }) => format!("REVEAL {} {}", x, y),
+            ClientMessage::Move(Move::Flag { x, y }) => format!("FLAG {} {}", x, y),
+            ClientMessage::Move(Move::Chord { x, y }) => format!("CHORD {} {}", x, y),
+            ClientMessage::NewGame => "NEW".to_owned(),
+        }
+    }
+
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace();
+        let message = match words.next()? {
+            "HELLO" => ClientMessage::Hello {
+                name: words.collect::<Vec<_>>().join(" "),
+            },
+            "NEW" => ClientMessage::NewGame,
+            command => {
+                let x = words.next()?.parse().ok()?;
+                let y = words.next()?.parse().ok()?;
+                ClientMessage::Move(match command {
+                    "REVEAL" => Move::Reveal { x, y },
+                    "FLAG" => Move::Flag { x, y },
+                    "CHORD" => Move::Chord { x, y },
+                    _ => return None,
+                })
+            }
+        };
+        Some(message)
+    }
+}
+
+/// What the server tells its clients.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerMessage {
+    Welcome {
+        player: usize,
+        mode: NetMode,
+    },
+    /// A fresh board; every cell seen so far is gone. The variant goes on
+    /// the end as `wrap` or `flat`, the neighborhood and the most rusts a
+    /// cell can hold, and a line without it is a classic board.
+    Board {
+        width: usize,
+        height: usize,
+        rusts: usize,
+        variant: Variant,
+    },
+    Open {
+        x: usize,
+        y: usize,
+        count: u8,
+    },
+    Flags {
+        x: usize,
+        y: usize,
+        flags: u8,
+    },
+    /// Sent for every rust once the board is over.
+    Rust {
+        x: usize,
+        y: usize,
+        rusts: u8,
+    },
+    /// The board `player` plays on is over. In co-op that is everyone's.
+    Over {
+        player: usize,
+        event: GameEvent,
+    },
+    Joined {
+        player: usize,
+        name: String,
+    },
+    Left {
+        player: usize,
+    },
+    /// How far a race opponent has got.
+    Progress {
+        player: usize,
+        revealed: usize,
+        safe: usize,
+    },
+    /// First to clear their board in a race.
+    Winner {
+        player: usize,
+    },
+    Error {
+        message: String,
+    },
+    /// Where the player's pointer is, in cells from the top left corner.
+    Cursor {
+        x: f32,
+        y: f32,
+    },
+    /// The game clock, which spectators keep ticking while it runs.
+    Clock {
+        seconds: f64,
+        running: bool,
+    },
+}
+
+impl ServerMessage {
+    pub fn to_line(&self) -> String {
+        match self {
+            ServerMessage::Welcome { player, mode } => {
+                format!("WELCOME {} {}", player, mode.as_str())
+            }
+            ServerMessage::Board {
+                width,
+                height,
+                rusts,
+                variant,
+            } => format!(
+                "BOARD {} {} {} {} {} {}",
+                width,
+                height,
+                rusts,
+                if variant.wrapping { "wrap" } else { "flat" },
+                variant.neighborhood.name(),
+                variant.max_rusts
+            ),
+            ServerMessage::Open { x, y, count } => format!("OPEN {} {} {}", x, y, count),
+            ServerMessage::Flags { x, y, flags } => format!("FLAGS {} {} {}", x, y, flags),
+            ServerMessage::Rust { x, y, rusts } => format!("RUST {} {} {}", x, y, rusts),
+            ServerMessage::Over {
+                player,
+                event: GameEvent::Solved,
+            } => format!("OVER {} solved", player),
+            ServerMessage::Over {
+                player,
+                event: GameEvent::Failed { x, y },
+            } => format!("OVER {} failed {} {}", player, x, y),
+            ServerMessage::Joined { player, name } => format!("JOINED {} {}", player, name),
+            ServerMessage::Left { player } => format!("LEFT {}", player),
+            ServerMessage::Progress {
+                player,
+                revealed,
+                safe,
+            } => format!("PROGRESS {} {} {}", player, revealed, safe),
+            ServerMessage::Winner { player } => format!("WINNER {}", player),
+            ServerMessage::Error { message } => format!("ERROR {}", message),
+            ServerMessage::Cursor { x, y } => format!("CURSOR {:.2} {:.2}", x, y),
+            ServerMessage::Clock { seconds, running } => format!(
+                "CLOCK {:.3} {}",
+                seconds,
+                if *running { "running" } else { "stopped" }
+            ),
+        }
+    }
+
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace();
+        let command = words.next()?;
+        match command {
+            "ERROR" => {
+                return Some(ServerMessage::Error {
+                    message: words.collect::<Vec<_>>().join(" "),
+                })
+            }
+            "CURSOR" => {
+                return Some(ServerMessage::Cursor {
+                    x: words.next()?.parse().ok()?,
+                    y: words.next()?.parse().ok()?,
+                })
+            }
+            "CLOCK" => {
+                return Some(ServerMessage::Clock {
+                    seconds: words.next()?.parse().ok()?,
+                    running: words.next()? == "running",
+                })
+            }
+            _ => (),
+        }
+        let mut number = || -> Option<usize> { words.next()?.parse().ok() };
+        let up_to = |word: Option<&str>, most: u8| -> Option<u8> {
+            word?.parse::<u8>().ok().filter(|&n| n <= most)
+        };
+        let message = match command {
+            "WELCOME" => {
+                let player = number()?;
+                ServerMessage::Welcome {
+                    player,
+                    mode: NetMode::parse(line.split_whitespace().nth(2)?)?,
+                }
+            }
+            "BOARD" => {
+                let (width, height, rusts) = (number()?, number()?, number()?);
+                let variant = match line.split_whitespace().collect::<Vec<_>>()[4..] {
+                    [] => Variant::default(),
+                    [wrapping, neighborhood, max_rusts] => Variant {
+                        wrapping: match wrapping {
+                            "wrap" => true,
+                            "flat" => false,
+                            _ => return None,
+                        },
+                        neighborhood: Neighborhood::from_name(neighborhood)?,
+                        max_rusts: max_rusts
+                            .parse()
+                            .ok()
+                            .filter(|max| (1..=MAX_STACK).contains(max))?,
+                    },
+                    _ => return None,
+                };
+                ServerMessage::Board {
+                    width,
+                    height,
+                    rusts,
+                    variant,
+                }
+            }
+            "OPEN" => ServerMessage::Open {
+                x: number()?,
+                y: number()?,
+                count: up_to(words.next(), MAX_COUNT)?,
+            },
+            "FLAGS" => ServerMessage::Flags {
+                x: number()?,
+                y: number()?,
+                flags: up_to(words.next(), MAX_STACK)?,
+            },
+            "RUST" => ServerMessage::Rust {
+                x: number()?,
+                y: number()?,
+                rusts: up_to(words.next(), MAX_STACK)?,
+            },
+            "OVER" => {
+                let player = number()?;
+                let rest: Vec<&str> = line.split_whitespace().skip(2).collect();
+                let event = match rest.as_slice() {
+                    ["solved"] => GameEvent::Solved,
+                    ["failed", x, y] => GameEvent::Failed {
+                        x: x.parse().ok()?,
+                        y: y.parse().ok()?,
+                    },
+                    _ => return None,
+                };
+                ServerMessage::Over { player, event }
+            }
+            "JOINED" => ServerMessage::Joined {
+                player: number()?,
+                name: line
+                    .split_whitespace()
+                    .skip(2)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            },
+            "LEFT" => ServerMessage::Left { player: number()? },
+            "PROGRESS" => ServerMessage::Progress {
+                player: number()?,
+                revealed: number()?,
+                safe: number()?,
+            },
+            "WINNER" => ServerMessage::Winner { player: number()? },
+            _ => return None,
+        };
+        Some(message)
+    }
+}
+
+/// Everything a newcomer needs to see `board` as it stands, told as
+/// `player`'s board.
+pub fn snapshot(board: &Board, player: usize) -> Vec<ServerMessage> {
+    let mut messages = vec![ServerMessage::Board {
+        width: board.width(),
+        height: board.height(),
+        rusts: board.total_rusts(),
+        variant: board.variant,
+    }];
+    let blank = blank_board(board.width(), board.height(), board.variant);
+    messages.extend(changes(&blank, board, player));
+    messages
+}
+
+/// What changed between two states of the same board, as the messages a
+/// client needs to catch up.
+pub fn changes(before: &Board, after: &Board, player: usize) -> Vec<ServerMessage> {
+    let mut messages = vec![];
+    for ((x, y), (old, new)) in after
+        .positions()
+        .zip(before.cells().iter().zip(after.cells()))
+    {
+        if old.is_hidden() && !new.is_hidden() {
+            messages.push(ServerMessage::Open {
+                x,
+                y,
+                count: new.rust_count(),
+            });
+        }
+        if old.flags() != new.flags() {
+            messages.push(ServerMessage::Flags {
+                x,
+                y,
+                flags: new.flags(),
+            });
+        }
+    }
+    if let (None, Some(event)) = (before.outcome, after.outcome) {
+        for ((x, y), cell) in after.positions().zip(after.cells()) {
+            if cell.is_rust() {
+                messages.push(ServerMessage::Rust {
+                    x,
+                    y,
+                    rusts: cell.rusts(),
+                });
+            }
+        }
+        messages.push(ServerMessage::Over { player, event });
+    }
+    messages
+}
+
+/// What brings someone who saw `before` up to `after`: the changes, or a
+/// whole snapshot once a new game has replaced the old one.
+pub fn catch_up(before: &Board, after: &Board, player: usize) -> Vec<ServerMessage> {
+    let restarted = before.width() != after.width()
+        || before.height() != after.height()
+        || before.variant != after.variant
+        || (before.outcome.is_some() && after.outcome.is_none())
+        || before
+            .cells()
+            .iter()
+            .zip(after.cells())
+            .any(|(old, new)| !old.is_hidden() && new.is_hidden());
+    if restarted {
+        snapshot(after, player)
+    } else {
+        changes(before, after, player)
+    }
+}
+
+/// A board with nothing known about it yet, which a client fills in from
+/// what the server tells it.
+pub fn blank_board(width: usize, height: usize, variant: Variant) -> Board {
+    let cells = vec![Cell::new(0); width * height];
+    Board::from_cells(width, height, cells, variant)
+}
+
+/// Copies what a message says about the cells onto a client's board.
+/// Messages about cells the board doesn't have, or with more rusts than
+/// its variant allows, are ignored rather than trusted.
+pub fn apply(board: &mut Board, message: &ServerMessage) {
+    let (width, height) = (board.width(), board.height());
+    let max_rusts = board.variant.max_rusts;
+    let max_count = board.variant.neighborhood.offsets().len() * max_rusts as usize;
+    match *message {
+        ServerMessage::Open { x, y, count }
+            if x < width && y < height && count as usize <= max_count =>
+        {
+            let cell = board.cell_mut(x, y);
+            cell.set_hidden(false);
+            cell.set_rust_count(count);
+        }
+        ServerMessage::Flags { x, y, flags } if x < width && y < height && flags <= max_rusts => {
+            board.set_flags(x, y, flags)
+        }
+        ServerMessage::Rust { x, y, rusts } if x < width && y < height && rusts <= max_rusts => {
+            board.cell_mut(x, y).set_rusts(rusts)
+        }
+        _ => (),
+    }
+}
+
+/// A connection to a server. Messages are read on a thread of their own,
+/// so the game can pick them up each frame without blocking.
+pub struct Client {
+    stream: TcpStream,
+    incoming: Receiver<ServerMessage>,
+}
+
+impl Client {
+    pub fn connect(addr: &str, name: &str) -> io::Result<Client> {
+        let stream = TcpStream::connect(addr)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if let Some(message) = ServerMessage::from_line(&line) {
+                    if sender.send(message).is_err() {
+                        return;
+                    }
+                }
+            }
+            let _ = sender.send(ServerMessage::Error {
+                message: "disconnected".to_owned(),
+            });
+        });
+        let mut client = Client { stream, incoming };
+        client.send(&ClientMessage::Hello {
+            name: name.to_owned(),
+        })?;
+        Ok(client)
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        writeln!(self.stream, "{}", message.to_line())
+    }
+
+    /// Every message that arrived since the last call.
+    pub fn poll(&self) -> Vec<ServerMessage> {
+        self.incoming.try_iter().collect()
+    }
+}
+
+impl Drop for Client {
+    // the reader thread holds a clone of the stream, so dropping ours alone
+    // would leave the connection open
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Read-only watchers of a game on this machine. New ones are picked up
+/// without blocking, so the game can check for them every frame.
+pub struct Broadcaster {
+    listener: TcpListener,
+    // a queue per watcher, drained by a thread of its own so a watcher that
+    // stops reading can't hold up the game
+    watchers: Vec<SyncSender<String>>,
+}
+
+// how many sends a watcher can fall behind by before it is dropped, and how
+// long its thread waits on a full socket before giving up on it
+const WATCHER_BACKLOG: usize = 64;
+const WATCHER_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl Broadcaster {
+    pub fn bind(port: u16) -> io::Result<Broadcaster> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Broadcaster {
+            listener,
+            watchers: vec![],
+        })
+    }
+
+    /// Lets in anyone waiting to watch, greeting each with the messages
+    /// `greeting` makes, which is only called if somebody new turned up.
+    pub fn accept<F: FnOnce() -> Vec<ServerMessage>>(&mut self, greeting: F) {
+        let mut newcomers = vec![];
+        while let Ok((stream, _)) = self.listener.accept() {
+            newcomers.push(stream);
+        }
+        if newcomers.is_empty() {
+            return;
+        }
+        let greeting = lines(&greeting());
+        for stream in newcomers {
+            if let Ok(watcher) = watch(stream) {
+                if watcher.try_send(greeting.clone()).is_ok() {
+                    self.watchers.push(watcher);
+                }
+            }
+        }
+    }
+
+    /// Queues messages for every watcher, forgetting the ones that have
+    /// gone or fallen too far behind.
+    pub fn send(&mut self, messages: &[ServerMessage]) {
+        if messages.is_empty() {
+            return;
+        }
+        let lines = lines(messages);
+        self.watchers
+            .retain(|watcher| watcher.try_send(lines.clone()).is_ok());
+    }
+
+    pub fn watchers(&self) -> usize {
+        self.watchers.len()
+    }
+}
+
+// starts the thread that writes to one watcher, which ends once the
+// watcher is dropped or stops taking what it is sent
+fn watch(stream: TcpStream) -> io::Result<SyncSender<String>> {
+    // accepted streams don't inherit the listener's nonblocking mode
+    stream.set_nonblocking(false)?;
+    stream.set_write_timeout(Some(WATCHER_TIMEOUT))?;
+    let (sender, queue) = mpsc::sync_channel::<String>(WATCHER_BACKLOG);
+    thread::spawn(move || {
+        let mut stream = stream;
+        for lines in queue {
+            if stream.write_all(lines.as_bytes()).is_err() {
+                break;
+            }
+        }
+        let _ = stream.shutdown(Shutdown::Both);
+    });
+    Ok(sender)
+}
+
+fn lines(messages: &[ServerMessage]) -> String {
+    messages
+        .iter()
+        .map(|message| message.to_line() + "\n")
+        .collect()
+}
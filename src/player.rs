@@ -0,0 +1,156 @@
+use rand::prelude::*;
+
+use crate::board::{Board, GameEvent};
+use crate::solver::{self, Analysis};
+use crate::variant::Variant;
+
+/// Something a player can do to a cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Move {
+    Reveal { x: usize, y: usize },
+    Flag { x: usize, y: usize },
+    Chord { x: usize, y: usize },
+}
+
+impl Move {
+    /// Plays the move on the board, returning the event if it ended the game.
+    pub fn apply(self, board: &mut Board) -> Option<GameEvent> {
+        match self {
+            Move::Reveal { x, y } => board.reveal(x, y),
+            Move::Flag { x, y } => {
+                board.cycle_flag(x, y);
+                None
+            }
+            Move::Chord { x, y } => board.chord(x, y),
+        }
+    }
+
+    pub fn position(self) -> (usize, usize) {
+        match self {
+            Move::Reveal { x, y } | Move::Flag { x, y } | Move::Chord { x, y } => (x, y),
+        }
+    }
+}
+
+/// A board as a player sees it: uncovered numbers, flags and which cells are
+/// still hidden. Where the rusts are is not reachable through it.
+pub struct BoardView<'a> {
+    board: &'a Board,
+}
+
+impl<'a> BoardView<'a> {
+    pub fn new(board: &'a Board) -> Self {
+        BoardView { board }
+    }
+
+    pub fn width(&self) -> usize {
+        self.board.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.board.height()
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.board.variant
+    }
+
+    pub fn total_rusts(&self) -> usize {
+        self.board.total_rusts()
+    }
+
+    pub fn is_hidden(&self, x: usize, y: usize) -> bool {
+        self.board.cell(x, y).is_hidden()
+    }
+
+    pub fn flags(&self, x: usize, y: usize) -> u8 {
+        self.board.cell(x, y).flags()
+    }
+
+    /// The number on an uncovered cell, `None` while it is hidden.
+    pub fn rust_count(&self, x: usize, y: usize) -> Option<u8> {
+        let cell = self.board.cell(x, y);
+        if cell.is_hidden() {
+            None
+        } else {
+            Some(cell.rust_count())
+        }
+    }
+
+    pub fn neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        self.board.neighbors(x, y)
+    }
+
+    /// Runs the solver, which only uses what this view shows.
+    pub fn analyze(&self) -> Analysis {
+        solver::analyze(self.board)
+    }
+}
+
+/// A strategy that plays the game one move at a time.
+pub trait Player {
+    fn name(&self) -> &str;
+
+    fn next_move(&mut self, view: &BoardView) -> Move;
+}
+
+/// Uncovers hidden cells at random. The baseline every other bot should beat.
+pub struct RandomBot {
+    rng: StdRng,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        RandomBot {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Player for RandomBot {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn next_move(&mut self, view: &BoardView) -> Move {
+        let height = view.height();
+        let hidden: Vec<(usize, usize)> = (0..view.width())
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .filter(|&(x, y)| view.is_hidden(x, y) && view.flags(x, y) == 0)
+            .collect();
+        let &(x, y) = hidden.choose(&mut self.rng).unwrap_or(&(0, 0));
+        Move::Reveal { x, y }
+    }
+}
+
+/// Plays every move the solver can prove, flagging the rusts it finds, and
+/// otherwise guesses the cell least likely to hold a rust.
+pub struct SolverBot;
+
+impl Player for SolverBot {
+    fn name(&self) -> &str {
+        "solver"
+    }
+
+    fn next_move(&mut self, view: &BoardView) -> Move {
+        let analysis = view.analyze();
+        if let Some(&(x, y)) = analysis.safe.iter().find(|&&(x, y)| view.flags(x, y) == 0) {
+            return Move::Reveal { x, y };
+        }
+        if let Some(&(x, y)) = analysis.rusts.iter().find(|&&(x, y)| view.flags(x, y) == 0) {
+            return Move::Flag { x, y };
+        }
+        let mut best = None;
+        for (x, column) in analysis.probabilities.iter().enumerate() {
+            for (y, p) in column.iter().enumerate() {
+                if let Some(p) = p {
+                    if view.flags(x, y) == 0 && best.is_none_or(|(_, _, b)| *p < b) {
+                        best = Some((x, y, *p));
+                    }
+                }
+            }
+        }
+        let (x, y, _) = best.unwrap_or((0, 0, 0.0));
+        Move::Reveal { x, y }
+    }
+}
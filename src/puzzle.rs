@@ -0,0 +1,192 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+
+use crate::board::{Board, GameEvent};
+use crate::player::Move;
+use crate::solver;
+use crate::storage;
+use crate::text::{self, ParseError};
+
+const SOLVED_FILE: &str = "puzzles.txt";
+
+/// What a puzzle asks of the player. Either way every move has to follow
+/// from what is on the board; a guess still finishes the puzzle but it
+/// doesn't count as solved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Goal {
+    /// Uncover every safe cell.
+    ClearBoard,
+    /// Flag every rust, and nothing else, without uncovering them all.
+    FindRusts,
+}
+
+impl Goal {
+    pub fn label(self) -> &'static str {
+        match self {
+            Goal::ClearBoard => "CLEAR THE BOARD",
+            Goal::FindRusts => "FLAG EVERY RUST",
+        }
+    }
+}
+
+/// A hand-made board in the `text` format, partly uncovered already.
+#[derive(Debug, Clone, Copy)]
+pub struct Puzzle {
+    pub name: &'static str,
+    pub goal: Goal,
+    pub layout: &'static str,
+}
+
+impl Puzzle {
+    pub fn board(&self) -> Result<Board, ParseError> {
+        text::parse(self.layout)
+    }
+
+    pub fn is_complete(&self, board: &Board) -> bool {
+        match self.goal {
+            Goal::ClearBoard => board.outcome == Some(GameEvent::Solved),
+            Goal::FindRusts => {
+                !matches!(board.outcome, Some(GameEvent::Failed { .. }))
+                    && board
+                        .cells()
+                        .iter()
+                        .all(|cell| !cell.is_hidden() || cell.flags() == cell.rusts())
+            }
+        }
+    }
+}
+
+/// The bundled puzzles, easiest first.
+pub const PUZZLES: &[Puzzle] = &[
+    Puzzle {
+        name: "first steps",
+        goal: Goal::ClearBoard,
+        layout: include_str!("../resources/puzzles/first-steps.txt"),
+    },
+    Puzzle {
+        name: "one two one",
+        goal: Goal::FindRusts,
+        layout: include_str!("../resources/puzzles/one-two-one.txt"),
+    },
+    Puzzle {
+        name: "one two two one",
+        goal: Goal::FindRusts,
+        layout: include_str!("../resources/puzzles/one-two-two-one.txt"),
+    },
+    Puzzle {
+        name: "corridor",
+        goal: Goal::ClearBoard,
+        layout: include_str!("../resources/puzzles/corridor.txt"),
+    },
+    Puzzle {
+        name: "the long way",
+        goal: Goal::ClearBoard,
+        layout: include_str!("../resources/puzzles/the-long-way.txt"),
+    },
+    Puzzle {
+        name: "round the bend",
+        goal: Goal::ClearBoard,
+        layout: include_str!("../resources/puzzles/round-the-bend.txt"),
+    },
+    Puzzle {
+        name: "knight school",
+        goal: Goal::FindRusts,
+        layout: include_str!("../resources/puzzles/knight-school.txt"),
+    },
+];
+
+/// Whether the solver can prove `mv` right on the board as it stands:
+/// uncovering only proven safe cells and flagging only proven rusts.
+/// Taking flags off and chording around correct flags are always fine.
+pub fn is_deduced(board: &Board, mv: Move) -> bool {
+    let (x, y) = mv.position();
+    let cell = board.cell(x, y);
+    if !cell.is_hidden() && !matches!(mv, Move::Chord { .. }) {
+        return true;
+    }
+    let analysis = solver::analyze(board);
+    match mv {
+        Move::Reveal { .. } => cell.is_flagged() || analysis.safe.contains(&(x, y)),
+        // the flag about to wrap back to none is a take back
+        Move::Flag { .. } => {
+            cell.flags() == board.variant.max_rusts || analysis.rusts.contains(&(x, y))
+        }
+        Move::Chord { .. } => board.neighbors(x, y).into_iter().all(|(nx, ny)| {
+            let neighbor = board.cell(nx, ny);
+            !neighbor.is_hidden() || neighbor.is_flagged() || analysis.safe.contains(&(nx, ny))
+        }),
+    }
+}
+
+/// Plays the puzzle with nothing but the solver's certain moves, which
+/// succeeds only when its goal has exactly one answer that logic alone can
+/// reach. Returns the number of moves it took.
+pub fn validate(puzzle: &Puzzle) -> Result<usize, String> {
+    let mut board = puzzle.board().map_err(|e| e.to_string())?;
+    if board.outcome.is_some() || puzzle.is_complete(&board) {
+        return Err("already finished".to_owned());
+    }
+    let mut moves = 0;
+    while !puzzle.is_complete(&board) {
+        let analysis = solver::analyze(&board);
+        let mut progressed = false;
+        for &(x, y) in &analysis.rusts {
+            if board.cell(x, y).flags() == 0 {
+                board.cycle_flag(x, y);
+                progressed = true;
+                moves += 1;
+            }
+        }
+        if puzzle.is_complete(&board) {
+            break;
+        }
+        for &(x, y) in &analysis.safe {
+            if board.cell(x, y).is_hidden() {
+                board.reveal(x, y);
+                progressed = true;
+                moves += 1;
+            }
+        }
+        if let Some(GameEvent::Failed { x, y }) = board.outcome {
+            return Err(format!("the solver uncovered a rust at ({}, {})", x, y));
+        }
+        if !progressed {
+            return Err(format!("needs a guess after {} moves", moves));
+        }
+    }
+    Ok(moves)
+}
+
+/// Names of the puzzles solved without a guess, in the order they were.
+pub fn solved() -> io::Result<Vec<String>> {
+    match fs::read_to_string(storage::data_file(SOLVED_FILE)?) {
+        Ok(contents) => Ok(contents.lines().map(str::to_owned).collect()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn mark_solved(name: &str) -> io::Result<()> {
+    if solved()?.iter().any(|solved| solved == name) {
+        return Ok(());
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(storage::data_file(SOLVED_FILE)?)?;
+    writeln!(file, "{}", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_puzzles_need_no_guess() {
+        for puzzle in PUZZLES {
+            if let Err(e) = validate(puzzle) {
+                panic!("{}: {}", puzzle.name, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,482 @@
+//! JSON-RPC 2.0 control of a game, for scripts and bots written in other
+//! languages. Requests and responses are one JSON object per line; the
+//! `rpc` binary speaks it on stdin and stdout.
+//!
+//! ```text
+//! {"jsonrpc": "2.0", "id": 1, "method": "new_game", "params": {"difficulty": "easy", "seed": 7}}
+//! {"jsonrpc": "2.0", "id": 2, "method": "reveal", "params": {"x": 4, "y": 4}}
+//! ```
+//!
+//! Methods:
+//!
+//! - `new_game`: starts a game and returns its state. Takes either `board`,
+//!   a board in the `text` format, or any of `difficulty` (`easy`, `medium`
+//!   or `hard`), `seed`, `wrap`, `neighborhood` (`moore`, `knight` or
+//!   `radius2`) and `max_rusts`. On a generated board the first reveal is
+//!   always safe.
+//! - `reveal`, `flag`, `chord`: play a move at `x`, `y` and return the cells
+//!   it changed and the outcome.
+//! - `get_state`: the whole board as the player sees it.
+//! - `subscribe`, `unsubscribe`: while subscribed, every change to the game
+//!   is also sent as a notification, `started` with the state of a new game
+//!   and `changed` with what a move did.
+//!
+//! Cells are objects with `x`, `y`, `hidden` and `flags`, plus `count` once
+//! uncovered and `rusts` once the game is over. In a state they come as
+//! rows, so `cells[y][x]`. The outcome is null until the game is `solved`
+//! or `failed`, and a failed game also names the `fatal` cell.
+
+use serde_json::{json, Map, Value};
+
+use crate::board::{Board, GameEvent, EASY, HARD, MEDIUM};
+use crate::player::Move;
+use crate::text;
+use crate::variant::{Neighborhood, Variant};
+
+// error codes from the JSON-RPC spec, plus one of our own
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const NO_GAME: i64 = -32000;
+
+/// Why a call failed, sent back as the response's `error`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn params(message: impl Into<String>) -> Self {
+        RpcError::new(INVALID_PARAMS, message)
+    }
+}
+
+/// One client's game.
+#[derive(Default)]
+pub struct Session {
+    board: Option<Board>,
+    // whether the first reveal is made safe, which boards read from text
+    // are not: they are played exactly as written
+    safe_start: bool,
+    subscribed: bool,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    /// Handles one line from the client and returns the lines to send back:
+    /// any notifications the call set off, then its response. Notifications
+    /// from the client get no response.
+    pub fn handle(&mut self, line: &str) -> Vec<String> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                return vec![response(
+                    Value::Null,
+                    Err(RpcError::new(PARSE_ERROR, e.to_string())),
+                )]
+            }
+        };
+        let id = request.get("id").cloned();
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(method) if request.get("jsonrpc") == Some(&json!("2.0")) => method,
+            _ => {
+                return vec![response(
+                    id.unwrap_or(Value::Null),
+                    Err(RpcError::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request")),
+                )]
+            }
+        };
+        let params = match request.get("params") {
+            None => Map::new(),
+            Some(Value::Object(params)) => params.clone(),
+            Some(_) => {
+                return vec![response(
+                    id.unwrap_or(Value::Null),
+                    Err(RpcError::params("params must be an object")),
+                )]
+            }
+        };
+        let mut notifications = vec![];
+        let result = self.call(method, &params, &mut notifications);
+        let mut lines: Vec<String> = if self.subscribed {
+            notifications
+                .into_iter()
+                .map(|(method, params)| {
+                    json!({"jsonrpc": "2.0", "method": method, "params": params}).to_string()
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        if let Some(id) = id {
+            lines.push(response(id, result));
+        }
+        lines
+    }
+
+    fn call(
+        &mut self,
+        method: &str,
+        params: &Map<String, Value>,
+        notifications: &mut Vec<(&'static str, Value)>,
+    ) -> Result<Value, RpcError> {
+        match method {
+            "new_game" => {
+                let (board, safe_start) = new_board(params)?;
+                let state = state(&board);
+                self.board = Some(board);
+                self.safe_start = safe_start;
+                notifications.push(("started", state.clone()));
+                Ok(state)
+            }
+            "reveal" | "flag" | "chord" => {
+                let board = self
+                    .board
+                    .as_mut()
+                    .ok_or_else(|| RpcError::new(NO_GAME, "no game, call new_game first"))?;
+                let (x, y) = position(board, params)?;
+                let mv = match method {
+                    "reveal" => Move::Reveal { x, y },
+                    "flag" => Move::Flag { x, y },
+                    _ => Move::Chord { x, y },
+                };
+                let result = play(board, self.safe_start, mv);
+                notifications.push(("changed", result.clone()));
+                Ok(result)
+            }
+            "get_state" => self
+                .board
+                .as_ref()
+                .map(state)
+                .ok_or_else(|| RpcError::new(NO_GAME, "no game, call new_game first")),
+            "subscribe" => {
+                self.subscribed = true;
+                Ok(json!(true))
+            }
+            "unsubscribe" => {
+                self.subscribed = false;
+                Ok(json!(true))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("no method '{}'", method),
+            )),
+        }
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> String {
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": e.code, "message": e.message},
+        }),
+    }
+    .to_string()
+}
+
+// the board `new_game` asks for, and whether its first reveal is made safe
+pub(crate) fn new_board(params: &Map<String, Value>) -> Result<(Board, bool), RpcError> {
+    if let Some(layout) = params.get("board") {
+        let layout = layout
+            .as_str()
+            .ok_or_else(|| RpcError::params("board must be a string"))?;
+        let board = text::parse(layout).map_err(|e| RpcError::params(e.to_string()))?;
+        return Ok((board, false));
+    }
+    let difficulty = match params.get("difficulty").map(Value::as_str) {
+        None | Some(Some("easy")) => EASY,
+        Some(Some("medium")) => MEDIUM,
+        Some(Some("hard")) => HARD,
+        _ => return Err(RpcError::params("difficulty must be easy, medium or hard")),
+    };
+    let mut variant = Variant::default();
+    if let Some(wrap) = params.get("wrap") {
+        variant.wrapping = wrap
+            .as_bool()
+            .ok_or_else(|| RpcError::params("wrap must be true or false"))?;
+    }
+    if let Some(neighborhood) = params.get("neighborhood") {
+        variant.neighborhood = match neighborhood.as_str() {
+            Some("moore") => Neighborhood::Moore,
+            Some("knight") => Neighborhood::Knight,
+            Some("radius2") => Neighborhood::Radius2,
+            _ => {
+                return Err(RpcError::params(
+                    "neighborhood must be moore, knight or radius2",
+                ))
+            }
+        };
+    }
+    if let Some(max_rusts) = params.get("max_rusts") {
+        variant.max_rusts = max_rusts
+            .as_u64()
+            .filter(|max| (1..=9).contains(max))
+            .ok_or_else(|| RpcError::params("max_rusts must be from 1 to 9"))?
+            as u8;
+    }
+    let mut board = match params.get("seed") {
+        None => Board::new(difficulty, variant),
+        Some(seed) => {
+            let seed = seed
+                .as_u64()
+                .ok_or_else(|| RpcError::params("seed must be a whole number"))?;
+            Board::from_seed(difficulty, variant, seed)
+        }
+    };
+    board.calculate_rust_count();
+    Ok((board, true))
+}
+
+// plays `mv` and returns the outcome and the cells it changed
+pub(crate) fn play(board: &mut Board, safe_start: bool, mv: Move) -> Value {
+    let before = board.clone();
+    if let (true, 0, Move::Reveal { x, y }) = (safe_start, board.revealed_safe(), mv) {
+        board.clear_rusts_at(x, y);
+    }
+    if board.outcome.is_none() {
+        mv.apply(board);
+    }
+    let mut result = outcome(board);
+    result["cells"] = changes(&before, board);
+    result
+}
+
+fn position(board: &Board, params: &Map<String, Value>) -> Result<(usize, usize), RpcError> {
+    let coordinate = |name: &str, size: usize| {
+        params
+            .get(name)
+            .and_then(Value::as_u64)
+            .map(|n| n as usize)
+            .filter(|&n| n < size)
+            .ok_or_else(|| {
+                RpcError::params(format!("{} must be a whole number below {}", name, size))
+            })
+    };
+    Ok((
+        coordinate("x", board.width())?,
+        coordinate("y", board.height())?,
+    ))
+}
+
+fn cell(board: &Board, x: usize, y: usize) -> Value {
+    let cell = board.cell(x, y);
+    let mut value = json!({"x": x, "y": y, "hidden": cell.is_hidden(), "flags": cell.flags()});
+    if !cell.is_hidden() {
+        value["count"] = json!(cell.rust_count());
+    }
+    if board.outcome.is_some() {
+        value["rusts"] = json!(cell.rusts());
+    }
+    value
+}
+
+// every cell that looks different now, which once the game is over
+// includes every rust
+fn changes(before: &Board, after: &Board) -> Value {
+    let mut changed = vec![];
+    for y in 0..after.height() {
+        for x in 0..after.width() {
+            let now = cell(after, x, y);
+            if cell(before, x, y) != now {
+                changed.push(now);
+            }
+        }
+    }
+    Value::Array(changed)
+}
+
+fn outcome(board: &Board) -> Value {
+    match board.outcome {
+        None => json!({ "outcome": null }),
+        Some(GameEvent::Solved) => json!({"outcome": "solved"}),
+        Some(GameEvent::Failed { x, y }) => {
+            json!({"outcome": "failed", "fatal": {"x": x, "y": y}})
+        }
+    }
+}
+
+pub(crate) fn state(board: &Board) -> Value {
+    let variant = board.variant;
+    let mut state = outcome(board);
+    state["width"] = json!(board.width());
+    state["height"] = json!(board.height());
+    state["rusts"] = json!(board.total_rusts());
+    state["variant"] = json!({
+        "wrap": variant.wrapping,
+        "neighborhood": match variant.neighborhood {
+            Neighborhood::Moore => "moore",
+            Neighborhood::Knight => "knight",
+            Neighborhood::Radius2 => "radius2",
+        },
+        "max_rusts": variant.max_rusts,
+    });
+    state["revealed"] = json!(board.revealed_safe());
+    state["safe_cells"] = json!(board.safe_cells());
+    state["flags"] = json!(board.flags_placed());
+    state["cells"] = (0..board.height())
+        .map(|y| {
+            (0..board.width())
+                .map(|x| cell(board, x, y))
+                .collect::<Value>()
+        })
+        .collect();
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the lines `session` sends back for `request`, parsed
+    fn send(session: &mut Session, request: Value) -> Vec<Value> {
+        session
+            .handle(&request.to_string())
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn new_game(layout: &str) -> Session {
+        let mut session = Session::new();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "new_game",
+            "params": {"board": layout},
+        });
+        assert!(send(&mut session, request)[0].get("result").is_some());
+        session
+    }
+
+    #[test]
+    fn reveal_returns_the_changed_cells() {
+        let mut session = new_game("*..\n...");
+        let reply = send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "reveal", "params": {"x": 2, "y": 1}}),
+        );
+        assert_eq!(reply.len(), 1);
+        assert_eq!(reply[0]["id"], 1);
+        let result = &reply[0]["result"];
+        assert_eq!(result["outcome"], Value::Null);
+        // the blank corner floods out as far as the numbers by the rust
+        let cells = result["cells"].as_array().unwrap();
+        assert!(cells
+            .iter()
+            .any(|cell| cell["x"] == 2 && cell["y"] == 1 && cell["count"] == 0));
+        assert!(cells.iter().all(|cell| cell["hidden"] == false));
+    }
+
+    #[test]
+    fn out_of_range_moves_are_invalid_params() {
+        let mut session = new_game("*..\n...");
+        let reply = send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "flag", "params": {"x": 3, "y": 0}}),
+        );
+        assert_eq!(reply[0]["id"], 1);
+        assert_eq!(reply[0]["error"]["code"], INVALID_PARAMS);
+        assert!(reply[0].get("result").is_none());
+    }
+
+    #[test]
+    fn bad_requests_are_errors() {
+        let mut session = Session::new();
+        let reply: Value = serde_json::from_str(&session.handle("not json")[0]).unwrap();
+        assert_eq!(reply["id"], Value::Null);
+        assert_eq!(reply["error"]["code"], PARSE_ERROR);
+
+        let reply = send(&mut session, json!({"id": 1, "method": "get_state"}));
+        assert_eq!(reply[0]["id"], 1);
+        assert_eq!(reply[0]["error"]["code"], INVALID_REQUEST);
+
+        let reply = send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 2, "method": "get_state", "params": [1]}),
+        );
+        assert_eq!(reply[0]["error"]["code"], INVALID_PARAMS);
+
+        let reply = send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 3, "method": "jump"}),
+        );
+        assert_eq!(reply[0]["error"]["code"], METHOD_NOT_FOUND);
+
+        let reply = send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 4, "method": "get_state"}),
+        );
+        assert_eq!(reply[0]["error"]["code"], NO_GAME);
+    }
+
+    #[test]
+    fn subscribers_hear_about_changes() {
+        let mut session = new_game("*..\n...");
+        let reply = send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "subscribe"}),
+        );
+        assert_eq!(
+            reply,
+            vec![json!({"jsonrpc": "2.0", "id": 1, "result": true})]
+        );
+
+        let reply = send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 2, "method": "reveal", "params": {"x": 0, "y": 0}}),
+        );
+        assert_eq!(reply.len(), 2);
+        assert_eq!(reply[0]["method"], "changed");
+        assert!(reply[0].get("id").is_none());
+        assert_eq!(reply[0]["params"]["outcome"], "failed");
+        assert_eq!(reply[0]["params"], reply[1]["result"]);
+
+        let reply = send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 3, "method": "new_game", "params": {"seed": 1}}),
+        );
+        assert_eq!(reply[0]["method"], "started");
+        assert_eq!(reply[0]["params"]["width"], reply[1]["result"]["width"]);
+
+        send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 4, "method": "unsubscribe"}),
+        );
+        let reply = send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 5, "method": "get_state"}),
+        );
+        assert_eq!(reply.len(), 1);
+    }
+
+    #[test]
+    fn notifications_get_no_response() {
+        let mut session = new_game("*..\n...");
+        let reply = send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "method": "flag", "params": {"x": 0, "y": 0}}),
+        );
+        assert!(reply.is_empty());
+        // but the move was still played
+        let reply = send(
+            &mut session,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "get_state"}),
+        );
+        assert_eq!(reply[0]["result"]["flags"], 1);
+    }
+}
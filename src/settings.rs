@@ -0,0 +1,508 @@
+//! The player's preferences, kept in `settings.toml` in the data directory.
+//! The game writes the file whenever something changes on its Settings
+//! screen and picks up edits made to it by hand while it runs.
+//!
+//! ```toml
+//! theme = "rust"              # rust, steel or mono
+//! difficulty = "easy"         # the board the game starts on
+//! safe_first_click = "reroll" # reroll, move or off
+//! question_marks = false
+//! chording = "button"         # button, click or off
+//! animation_speed = 1.0       # 0 turns the rust reveal off
+//!
+//! [keys]
+//! hint = "H"
+//! analysis = "Ctrl+A"
+//!
+//! [mouse]
+//! Left = "reveal"
+//! Right = "flag"
+//! "Ctrl+Left" = "flag"
+//! ```
+//!
+//! Anything left out keeps its default, except that a `[mouse]` table
+//! replaces the default buttons as a whole. Bindings are written as in
+//! `input`: `Ctrl+`, `Shift+` and `Alt+` in front of a key such as `A` to
+//! `Z`, `Num0` to `Num9`, `F1` to `F12`, `Space`, `Tab`, `Return`,
+//! `Escape`, `Backspace`, `Comma`, `Period`, `Slash`, `Minus` or `Equals`,
+//! or of a mouse button: `Left`, `Middle`, `Right`, `X1` or `X2`.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use toml::Value;
+
+use crate::board::{EASY, HARD, MEDIUM};
+use crate::error::Error;
+use crate::input::{self, Binding, ClickAction, KeyAction, Modifiers};
+use crate::storage;
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+// animation speeds the Settings screen steps through
+const SPEEDS: [f32; 5] = [0.0, 0.5, 1.0, 2.0, 4.0];
+
+/// Colours for the window, as RGB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub background: (u8, u8, u8),
+    /// Buttons, cards and the dividing line.
+    pub panel: (u8, u8, u8),
+    /// Active buttons and question marks.
+    pub accent: (u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Theme {
+    Rust,
+    Steel,
+    Mono,
+}
+
+impl Theme {
+    pub fn palette(self) -> Palette {
+        match self {
+            Theme::Rust => Palette {
+                background: (75, 27, 34),
+                panel: (21, 4, 12),
+                accent: (183, 65, 14),
+            },
+            Theme::Steel => Palette {
+                background: (38, 50, 64),
+                panel: (14, 20, 28),
+                accent: (70, 130, 180),
+            },
+            Theme::Mono => Palette {
+                background: (60, 60, 60),
+                panel: (20, 20, 20),
+                accent: (140, 140, 140),
+            },
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Theme::Rust => Theme::Steel,
+            Theme::Steel => Theme::Mono,
+            Theme::Mono => Theme::Rust,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Rust => "rust",
+            Theme::Steel => "steel",
+            Theme::Mono => "mono",
+        }
+    }
+}
+
+/// What happens when the first reveal of a classic game hits a rust.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SafeStart {
+    /// Roll new boards until the cell is safe.
+    Reroll,
+    /// Move the rusts out of the cell, as the daily board does.
+    Move,
+    /// Nothing, the game is lost.
+    Off,
+}
+
+impl SafeStart {
+    pub fn next(self) -> Self {
+        match self {
+            SafeStart::Reroll => SafeStart::Move,
+            SafeStart::Move => SafeStart::Off,
+            SafeStart::Off => SafeStart::Reroll,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SafeStart::Reroll => "reroll",
+            SafeStart::Move => "move",
+            SafeStart::Off => "off",
+        }
+    }
+}
+
+/// How a chord is played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Chording {
+    /// With the mouse button bound to chord, the middle one by default.
+    Button,
+    /// With the chord button, or by revealing an uncovered number.
+    Click,
+    Off,
+}
+
+impl Chording {
+    pub fn next(self) -> Self {
+        match self {
+            Chording::Button => Chording::Click,
+            Chording::Click => Chording::Off,
+            Chording::Off => Chording::Button,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Chording::Button => "button",
+            Chording::Click => "click",
+            Chording::Off => "off",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub theme: Theme,
+    /// The board a new window starts on, as (rusts, cells per row).
+    pub difficulty: (usize, usize),
+    pub safe_start: SafeStart,
+    /// Whether flagging goes on to a question mark after the last flag.
+    pub question_marks: bool,
+    pub chording: Chording,
+    /// How fast the rusts pop up after a loss, 1 as designed and 0 for no
+    /// animation at all.
+    pub animation_speed: f32,
+    /// One key for each action, in the order of `KeyAction::ALL`.
+    keys: Vec<(Binding, KeyAction)>,
+    /// Any number of mouse buttons for each action, in the order the file
+    /// lists them: sorted by name.
+    mouse: Vec<(Binding, ClickAction)>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            theme: Theme::Rust,
+            difficulty: EASY,
+            safe_start: SafeStart::Reroll,
+            question_marks: false,
+            chording: Chording::Button,
+            animation_speed: 1.0,
+            keys: KeyAction::ALL
+                .iter()
+                .map(|&action| {
+                    (
+                        Binding::new(action.default_key(), Modifiers::default()),
+                        action,
+                    )
+                })
+                .collect(),
+            // Ctrl+click flags on trackpads with a single button
+            mouse: vec![
+                (
+                    Binding::new(
+                        "Left",
+                        Modifiers {
+                            ctrl: true,
+                            ..Modifiers::default()
+                        },
+                    ),
+                    ClickAction::Flag,
+                ),
+                (
+                    Binding::new("Left", Modifiers::default()),
+                    ClickAction::Reveal,
+                ),
+                (
+                    Binding::new("Middle", Modifiers::default()),
+                    ClickAction::Chord,
+                ),
+                (
+                    Binding::new("Right", Modifiers::default()),
+                    ClickAction::Flag,
+                ),
+            ],
+        }
+    }
+}
+
+impl Settings {
+    pub fn key(&self, action: KeyAction) -> &Binding {
+        &self.keys[action as usize].0
+    }
+
+    /// Binds `binding` to `action`. An action that had it before gets
+    /// `action`'s old key, so none is left without one.
+    pub fn set_key(&mut self, action: KeyAction, binding: Binding) {
+        let old = self.key(action).clone();
+        for (key, bound) in &mut self.keys {
+            if *bound == action {
+                *key = binding.clone();
+            } else if *key == binding {
+                *key = old.clone();
+            }
+        }
+    }
+
+    /// The action for the key named `key` with the modifiers `held`.
+    pub fn key_action(&self, key: &str, held: Modifiers) -> Option<KeyAction> {
+        input::lookup(&self.keys, key, held)
+    }
+
+    /// The mouse buttons bound to `action`.
+    pub fn buttons(&self, action: ClickAction) -> impl Iterator<Item = &Binding> {
+        self.mouse
+            .iter()
+            .filter(move |&&(_, bound)| bound == action)
+            .map(|(binding, _)| binding)
+    }
+
+    /// Binds `binding` to `action` in place of whatever it did before, or
+    /// unbinds it if it already did `action`.
+    pub fn toggle_button(&mut self, binding: Binding, action: ClickAction) {
+        let old = self
+            .mouse
+            .iter()
+            .position(|(bound, _)| *bound == binding)
+            .map(|i| self.mouse.remove(i).1);
+        if old != Some(action) {
+            self.mouse.push((binding, action));
+            self.mouse.sort_by_key(|(binding, _)| binding.to_string());
+        }
+    }
+
+    /// The action for the mouse button named `button` with the modifiers
+    /// `held`.
+    pub fn click_action(&self, button: &str, held: Modifiers) -> Option<ClickAction> {
+        input::lookup(&self.mouse, button, held)
+    }
+
+    /// The animation speed after this one, wrapping back to none.
+    pub fn next_speed(&self) -> f32 {
+        SPEEDS
+            .iter()
+            .copied()
+            .find(|&speed| speed > self.animation_speed)
+            .unwrap_or(SPEEDS[0])
+    }
+
+    pub fn difficulty_name(&self) -> &'static str {
+        match self.difficulty {
+            MEDIUM => "medium",
+            HARD => "hard",
+            _ => "easy",
+        }
+    }
+
+    /// Reads settings from the text of a settings file.
+    pub fn parse(text: &str) -> Result<Settings, String> {
+        let table = match text.parse::<Value>().map_err(|e| e.to_string())? {
+            Value::Table(table) => table,
+            _ => return Err("not a table".to_owned()),
+        };
+        let mut settings = Settings::default();
+        for (key, value) in &table {
+            let name = || {
+                value
+                    .as_str()
+                    .ok_or_else(|| format!("{} must be a string", key))
+            };
+            match key.as_str() {
+                "theme" => {
+                    settings.theme = match name()? {
+                        "rust" => Theme::Rust,
+                        "steel" => Theme::Steel,
+                        "mono" => Theme::Mono,
+                        _ => return Err("theme must be rust, steel or mono".to_owned()),
+                    }
+                }
+                "difficulty" => {
+                    settings.difficulty = match name()? {
+                        "easy" => EASY,
+                        "medium" => MEDIUM,
+                        "hard" => HARD,
+                        _ => return Err("difficulty must be easy, medium or hard".to_owned()),
+                    }
+                }
+                "safe_first_click" => {
+                    settings.safe_start = match name()? {
+                        "reroll" => SafeStart::Reroll,
+                        "move" => SafeStart::Move,
+                        "off" => SafeStart::Off,
+                        _ => return Err("safe_first_click must be reroll, move or off".to_owned()),
+                    }
+                }
+                "question_marks" => {
+                    settings.question_marks = value
+                        .as_bool()
+                        .ok_or("question_marks must be true or false")?
+                }
+                "chording" => {
+                    settings.chording = match name()? {
+                        "button" => Chording::Button,
+                        "click" => Chording::Click,
+                        "off" => Chording::Off,
+                        _ => return Err("chording must be button, click or off".to_owned()),
+                    }
+                }
+                "animation_speed" => {
+                    settings.animation_speed = value
+                        .as_float()
+                        .or_else(|| value.as_integer().map(|n| n as f64))
+                        .filter(|speed| (0.0..=10.0).contains(speed))
+                        .ok_or("animation_speed must be a number from 0 to 10")?
+                        as f32
+                }
+                "keys" => {
+                    let keys = value.as_table().ok_or("keys must be a table")?;
+                    for (action, key) in keys {
+                        let action = KeyAction::from_name(action)
+                            .ok_or_else(|| format!("no action '{}' to bind", action))?;
+                        let key = key
+                            .as_str()
+                            .and_then(Binding::key)
+                            .ok_or_else(|| format!("no key {} for {}", key, action.name()))?;
+                        settings.set_key(action, key);
+                    }
+                }
+                "mouse" => {
+                    let buttons = value.as_table().ok_or("mouse must be a table")?;
+                    settings.mouse.clear();
+                    for (button, action) in buttons {
+                        let binding = Binding::button(button)
+                            .ok_or_else(|| format!("no mouse button '{}'", button))?;
+                        let action = action
+                            .as_str()
+                            .and_then(ClickAction::from_name)
+                            .ok_or_else(|| format!("{} must be reveal, flag or chord", button))?;
+                        settings.mouse.push((binding, action));
+                    }
+                    settings
+                        .mouse
+                        .sort_by_key(|(binding, _)| binding.to_string());
+                }
+                _ => return Err(format!("unknown setting '{}'", key)),
+            }
+        }
+        Ok(settings)
+    }
+
+    /// The settings as the text of a settings file, every one spelled out.
+    pub fn write(&self) -> String {
+        let mut text = format!(
+            "theme = \"{}\"\n\
+             difficulty = \"{}\"\n\
+             safe_first_click = \"{}\"\n\
+             question_marks = {}\n\
+             chording = \"{}\"\n\
+             animation_speed = {:?}\n\
+             \n\
+             [keys]\n",
+            self.theme.name(),
+            self.difficulty_name(),
+            self.safe_start.name(),
+            self.question_marks,
+            self.chording.name(),
+            self.animation_speed,
+        );
+        for (key, action) in &self.keys {
+            text.push_str(&format!("{} = \"{}\"\n", action.name(), key));
+        }
+        text.push_str("\n[mouse]\n");
+        for (button, action) in &self.mouse {
+            text.push_str(&format!("\"{}\" = \"{}\"\n", button, action.name()));
+        }
+        text
+    }
+}
+
+/// Where the settings file lives.
+pub fn path() -> Result<PathBuf, Error> {
+    storage::data_file(SETTINGS_FILE).map_err(|e| Error::save("find the settings file", e))
+}
+
+/// When the settings file last changed, `None` while there isn't one.
+pub fn modified() -> Option<SystemTime> {
+    path()
+        .ok()
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|metadata| metadata.modified().ok())
+}
+
+/// The saved settings, or the defaults before anything has been saved.
+pub fn load() -> Result<Settings, Error> {
+    let path = path()?;
+    match fs::read_to_string(&path) {
+        Ok(text) => Settings::parse(&text).map_err(|message| Error::Config { path, message }),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+        Err(e) => Err(Error::Config {
+            path,
+            message: e.to_string(),
+        }),
+    }
+}
+
+pub fn save(settings: &Settings) -> Result<(), Error> {
+    fs::write(path()?, settings.write()).map_err(|e| Error::save("save settings", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_settings_read_back() {
+        let mut settings = Settings {
+            theme: Theme::Steel,
+            difficulty: HARD,
+            safe_start: SafeStart::Move,
+            question_marks: true,
+            chording: Chording::Click,
+            animation_speed: 0.5,
+            ..Settings::default()
+        };
+        settings.set_key(KeyAction::Hint, Binding::key("Ctrl+F3").unwrap());
+        settings.set_key(KeyAction::Load, Binding::key("A").unwrap());
+        settings.toggle_button(Binding::button("Shift+Left").unwrap(), ClickAction::Chord);
+        settings.toggle_button(Binding::button("Middle").unwrap(), ClickAction::Chord);
+        assert_eq!(Settings::parse(&settings.write()), Ok(settings.clone()));
+        assert_eq!(
+            Settings::parse(&Settings::default().write()),
+            Ok(Settings::default())
+        );
+    }
+
+    #[test]
+    fn missing_settings_keep_their_defaults() {
+        assert_eq!(Settings::parse(""), Ok(Settings::default()));
+        let settings = Settings::parse("theme = \"mono\"\n[keys]\nhint = \"F1\"").unwrap();
+        assert_eq!(settings.theme, Theme::Mono);
+        assert_eq!(settings.key(KeyAction::Hint).to_string(), "F1");
+        assert_eq!(settings.safe_start, SafeStart::Reroll);
+        assert_eq!(settings.key(KeyAction::Export).to_string(), "E");
+        assert_eq!(
+            settings.click_action("Left", Modifiers::default()),
+            Some(ClickAction::Reveal)
+        );
+    }
+
+    #[test]
+    fn bad_settings_are_rejected() {
+        for text in &[
+            "theme = \"plaid\"",
+            "theme = 3",
+            "safe_first_click = \"maybe\"",
+            "difficulty = \"expert\"",
+            "question_marks = \"yes\"",
+            "animation_speed = 11",
+            "colour = \"red\"",
+            "[keys]\nhint = \"Num10\"",
+            "[keys]\nhint = \"Meta+H\"",
+            "[keys]\ncheat = \"C\"",
+            "[mouse]\nWheel = \"reveal\"",
+            "[mouse]\nLeft = \"dig\"",
+            // not TOML at all
+            "theme =",
+            "theme \"rust\"",
+            "[keys",
+        ] {
+            assert!(Settings::parse(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,881 @@
+use crate::board::Board;
+use rand::prelude::*;
+
+// search nodes a single frontier component may use before the solver gives
+// up on counting it exactly and samples it instead
+const SEARCH_BUDGET: usize = 200_000;
+// how many random layouts to draw for a component that is too big to count,
+// and the search nodes each of them may use
+const SAMPLES: usize = 400;
+const SAMPLE_BUDGET: usize = 2_000;
+
+/// What the solver suggests doing next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hint {
+    /// The cell cannot hold a rust.
+    Safe { x: usize, y: usize },
+    /// The cell holds a rust whatever the rest of the board looks like.
+    Rust { x: usize, y: usize },
+    /// Nothing is certain, this is the cell least likely to hold a rust.
+    Guess {
+        x: usize,
+        y: usize,
+        probability: f32,
+    },
+}
+
+/// Everything the solver could work out from the uncovered numbers and the
+/// total number of rusts. It only looks at what the player can see, never
+/// at where the rusts are, and ignores flags since those may be wrong.
+///
+/// Probabilities treat every layout of rusts that agrees with the board as
+/// equally likely. They are exact unless part of the board was too tangled
+/// to count, in which case that part is estimated from random samples.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    /// Chance of each cell holding at least one rust, indexed
+    /// `[x][y]`. `None` for uncovered cells.
+    pub probabilities: Vec<Vec<Option<f32>>>,
+    /// Hidden cells proven to hold no rust.
+    pub safe: Vec<(usize, usize)>,
+    /// Hidden cells proven to hold at least one rust.
+    pub rusts: Vec<(usize, usize)>,
+}
+
+// an uncovered number: the listed hidden cells must hold `total` rusts
+struct Constraint {
+    vars: Vec<usize>,
+    total: i32,
+}
+
+pub fn analyze(board: &Board) -> Analysis {
+    let (width, height) = (board.width(), board.height());
+    let max_rusts = board.variant.max_rusts;
+
+    // every hidden cell becomes a variable holding 0..=max_rusts rusts
+    let mut var_of = vec![vec![None; height]; width];
+    let mut positions = vec![];
+    for (x, column) in var_of.iter_mut().enumerate() {
+        for (y, var) in column.iter_mut().enumerate() {
+            if board.cell(x, y).is_hidden() {
+                *var = Some(positions.len());
+                positions.push((x, y));
+            }
+        }
+    }
+
+    let mut constraints = vec![];
+    for x in 0..width {
+        for y in 0..height {
+            let cell = board.cell(x, y);
+            if cell.is_hidden() || cell.is_rust() {
+                continue;
+            }
+            let vars: Vec<usize> = board
+                .neighbors(x, y)
+                .into_iter()
+                .filter_map(|(nx, ny)| var_of[nx][ny])
+                .collect();
+            if !vars.is_empty() {
+                constraints.push(Constraint {
+                    vars,
+                    total: cell.rust_count() as i32,
+                });
+            }
+        }
+    }
+
+    let mut var_constraints = vec![vec![]; positions.len()];
+    for (c, constraint) in constraints.iter().enumerate() {
+        for &v in &constraint.vars {
+            var_constraints[v].push(c);
+        }
+    }
+
+    let mut lo = vec![0u8; positions.len()];
+    let mut hi = vec![max_rusts; positions.len()];
+    propagate(&constraints, &mut lo, &mut hi);
+
+    // cells the simple deductions settled are certain either way
+    let mut odds: Vec<f64> = (0..positions.len())
+        .map(|v| if lo[v] > 0 { 1.0 } else { 0.0 })
+        .collect();
+    let mut certain: Vec<bool> = (0..positions.len()).map(|v| lo[v] == hi[v]).collect();
+    let fixed_rusts: usize = (0..positions.len())
+        .filter(|&v| lo[v] == hi[v])
+        .map(|v| lo[v] as usize)
+        .sum();
+
+    // the undecided cells split into independent groups that share numbers,
+    // plus the cells no number touches at all
+    let mut components = vec![];
+    let mut exhaustive = true;
+    let mut seen = vec![false; positions.len()];
+    let mut unconstrained = vec![];
+    for start in 0..positions.len() {
+        if seen[start] || lo[start] == hi[start] {
+            continue;
+        }
+        if var_constraints[start].is_empty() {
+            unconstrained.push(start);
+            continue;
+        }
+        let component =
+            collect_component(start, &constraints, &var_constraints, &lo, &hi, &mut seen);
+        let stats = match enumerate(&component, &constraints, &var_constraints, &lo, &hi) {
+            Some(stats) => stats,
+            None => {
+                exhaustive = false;
+                sample(&component, &constraints, &var_constraints, &lo, &hi)
+            }
+        };
+        if stats.is_empty() {
+            // not even a sample agreed with the numbers, know nothing
+            unconstrained.extend(component);
+        } else {
+            components.push((component, normalize(stats)));
+        }
+    }
+
+    // the rusts not pinned down yet are shared between the components and
+    // the untouched cells; weigh each component's rust count by how many
+    // ways the rest of the board can take the remainder
+    let left = board.total_rusts().saturating_sub(fixed_rusts);
+    let spreads = Spreads::new(unconstrained.len(), max_rusts, left);
+    let counts: Vec<Vec<f64>> = components
+        .iter()
+        .map(|(_, stats)| stats.iter().map(|(n, _)| *n).collect())
+        .collect();
+    let mut before = vec![vec![1.0]];
+    for c in 0..counts.len() {
+        before.push(convolve(&before[c], &counts[c], left));
+    }
+    let mut after = vec![vec![1.0]; counts.len() + 1];
+    for c in (0..counts.len()).rev() {
+        after[c] = convolve(&after[c + 1], &counts[c], left);
+    }
+    let all = &before[counts.len()];
+    let total: f64 = (0..all.len()).map(|s| all[s] * spreads.all(left - s)).sum();
+
+    for (c, (component, stats)) in components.iter().enumerate() {
+        let others = convolve(&before[c], &after[c + 1], left);
+        let weights: Vec<f64> = (0..stats.len())
+            .map(|k| {
+                (0..others.len())
+                    .filter(|&s| k + s <= left)
+                    .map(|s| others[s] * spreads.all(left - k - s))
+                    .sum()
+            })
+            .collect();
+        // decided without the weights, which can underflow on big boards
+        let possible: Vec<usize> = (0..stats.len())
+            .filter(|&k| {
+                (0..others.len())
+                    .any(|s| others[s] > 0.0 && k + s <= left && spreads.possible(left - k - s))
+            })
+            .collect();
+        for (i, &v) in component.iter().enumerate() {
+            let rusty: f64 = (0..stats.len()).map(|k| stats[k].1[i] * weights[k]).sum();
+            let never = possible.iter().all(|&k| stats[k].1[i] == 0.0);
+            let always = possible.iter().all(|&k| stats[k].1[i] == stats[k].0);
+            odds[v] = if total > 0.0 { rusty / total } else { 0.5 };
+            certain[v] = exhaustive && total > 0.0 && (never || always);
+            if certain[v] {
+                odds[v] = if never { 0.0 } else { 1.0 };
+            }
+        }
+    }
+
+    // every untouched cell is alike: it stays empty in whatever share of
+    // the remaining layouts leaves it out
+    if !unconstrained.is_empty() {
+        let rusty: f64 = (0..all.len())
+            .map(|s| all[s] * (spreads.all(left - s) - spreads.without_one(left - s)))
+            .sum();
+        let possible: Vec<usize> = (0..all.len())
+            .filter(|&s| all[s] > 0.0 && spreads.possible(left - s))
+            .map(|s| left - s)
+            .collect();
+        let never = possible.iter().all(|&r| r == 0);
+        let always = possible.iter().all(|&r| !spreads.possible_without_one(r));
+        let mut p = if total > 0.0 { rusty / total } else { 0.5 };
+        if never || always {
+            p = if never { 0.0 } else { 1.0 };
+        }
+        for &v in &unconstrained {
+            odds[v] = p;
+            certain[v] = exhaustive && total > 0.0 && (never || always);
+        }
+    }
+
+    let mut analysis = Analysis {
+        probabilities: vec![vec![None; height]; width],
+        safe: vec![],
+        rusts: vec![],
+    };
+    for (v, &(x, y)) in positions.iter().enumerate() {
+        analysis.probabilities[x][y] = Some(odds[v] as f32);
+        if certain[v] && odds[v] == 0.0 {
+            analysis.safe.push((x, y));
+        } else if certain[v] && odds[v] == 1.0 {
+            analysis.rusts.push((x, y));
+        }
+    }
+    analysis
+}
+
+/// Picks the most useful move for the player: a safe cell to uncover, then
+/// an unflagged rust, then the least risky guess.
+pub fn hint(board: &Board) -> Option<Hint> {
+    let analysis = analyze(board);
+    if let Some(&(x, y)) = analysis
+        .safe
+        .iter()
+        .find(|&&(x, y)| !board.cell(x, y).is_flagged())
+    {
+        return Some(Hint::Safe { x, y });
+    }
+    if let Some(&(x, y)) = analysis
+        .rusts
+        .iter()
+        .find(|&&(x, y)| !board.cell(x, y).is_flagged())
+    {
+        return Some(Hint::Rust { x, y });
+    }
+    let mut best: Option<Hint> = None;
+    for (x, column) in analysis.probabilities.iter().enumerate() {
+        for (y, p) in column.iter().enumerate() {
+            let p = match p {
+                Some(p) if !board.cell(x, y).is_flagged() => *p,
+                _ => continue,
+            };
+            match best {
+                Some(Hint::Guess { probability, .. }) if probability <= p => {}
+                _ => {
+                    best = Some(Hint::Guess {
+                        x,
+                        y,
+                        probability: p,
+                    })
+                }
+            }
+        }
+    }
+    best
+}
+
+// narrows every variable's range using one number at a time until nothing
+// changes. Catches the everyday deductions (all rusts found, all cells
+// must be rusts) without any search
+fn propagate(constraints: &[Constraint], lo: &mut [u8], hi: &mut [u8]) {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for constraint in constraints {
+            let min: i32 = constraint.vars.iter().map(|&v| lo[v] as i32).sum();
+            let max: i32 = constraint.vars.iter().map(|&v| hi[v] as i32).sum();
+            for &v in &constraint.vars {
+                // what the other cells can't cover must sit here, and this
+                // cell can't take more than the others leave over
+                let new_lo = (constraint.total - (max - hi[v] as i32)).max(lo[v] as i32);
+                let new_hi = (constraint.total - (min - lo[v] as i32)).min(hi[v] as i32);
+                if new_lo > lo[v] as i32 || new_hi < hi[v] as i32 {
+                    // a contradiction means the board was already broken,
+                    // keep the ranges sane rather than loop forever
+                    lo[v] = new_lo.min(new_hi).max(0) as u8;
+                    hi[v] = new_hi.max(new_lo).max(0) as u8;
+                    changed = true;
+                }
+            }
+        }
+    }
+}
+
+fn collect_component(
+    start: usize,
+    constraints: &[Constraint],
+    var_constraints: &[Vec<usize>],
+    lo: &[u8],
+    hi: &[u8],
+    seen: &mut [bool],
+) -> Vec<usize> {
+    let mut component = vec![start];
+    seen[start] = true;
+    let mut i = 0;
+    while i < component.len() {
+        for &c in &var_constraints[component[i]] {
+            for &v in &constraints[c].vars {
+                if !seen[v] && lo[v] != hi[v] {
+                    seen[v] = true;
+                    component.push(v);
+                }
+            }
+        }
+        i += 1;
+    }
+    component
+}
+
+// ways of spreading rusts over the cells no number touches, scaled down by
+// a common factor so huge boards stay within f64
+struct Spreads {
+    ln_all: Vec<f64>,
+    ln_without_one: Vec<f64>,
+    offset: f64,
+}
+
+impl Spreads {
+    fn new(cells: usize, max_rusts: u8, up_to: usize) -> Self {
+        let (ln_all, ln_without_one) = if max_rusts == 1 {
+            // plain binomials, from a table of log factorials
+            let mut ln_fact = vec![0.0; cells + 1];
+            for n in 1..=cells {
+                ln_fact[n] = ln_fact[n - 1] + (n as f64).ln();
+            }
+            let choose = |n: usize, r: usize| {
+                if r > n {
+                    f64::NEG_INFINITY
+                } else {
+                    ln_fact[n] - ln_fact[r] - ln_fact[n - r]
+                }
+            };
+            (
+                (0..=up_to).map(|r| choose(cells, r)).collect(),
+                (0..=up_to)
+                    .map(|r| {
+                        if cells == 0 {
+                            f64::NEG_INFINITY
+                        } else {
+                            choose(cells - 1, r)
+                        }
+                    })
+                    .collect(),
+            )
+        } else {
+            // stacked rusts: add the cells one at a time, each taking
+            // anywhere from none to max_rusts
+            let mut row = vec![f64::NEG_INFINITY; up_to + 1];
+            row[0] = 0.0;
+            let mut previous = row.clone();
+            for _ in 0..cells {
+                previous = row;
+                row = (0..=up_to)
+                    .map(|r| ln_sum((0..=(max_rusts as usize).min(r)).map(|t| previous[r - t])))
+                    .collect();
+            }
+            if cells == 0 {
+                previous = vec![f64::NEG_INFINITY; up_to + 1];
+            }
+            (row, previous)
+        };
+        let offset = ln_all
+            .iter()
+            .cloned()
+            .filter(|n| n.is_finite())
+            .fold(f64::NEG_INFINITY, f64::max);
+        Spreads {
+            ln_all,
+            ln_without_one,
+            offset: if offset.is_finite() { offset } else { 0.0 },
+        }
+    }
+
+    fn possible(&self, rusts: usize) -> bool {
+        self.ln_all[rusts].is_finite()
+    }
+
+    fn possible_without_one(&self, rusts: usize) -> bool {
+        self.ln_without_one[rusts].is_finite()
+    }
+
+    // ways to place `rusts` rusts in all of the cells
+    fn all(&self, rusts: usize) -> f64 {
+        (self.ln_all[rusts] - self.offset).exp()
+    }
+
+    // ways to place them while leaving one particular cell empty
+    fn without_one(&self, rusts: usize) -> f64 {
+        (self.ln_without_one[rusts] - self.offset).exp()
+    }
+}
+
+// log of a sum of numbers given as logs
+fn ln_sum<I: Iterator<Item = f64>>(logs: I) -> f64 {
+    let logs: Vec<f64> = logs.collect();
+    let max = logs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if !max.is_finite() {
+        return max;
+    }
+    max + logs.iter().map(|n| (n - max).exp()).sum::<f64>().ln()
+}
+
+// distribution of the rust total of two independent groups, dropping totals
+// past `limit`
+fn convolve(a: &[f64], b: &[f64], limit: usize) -> Vec<f64> {
+    let len = (a.len() + b.len() - 1).min(limit + 1);
+    let mut out = vec![0.0; len];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            if i + j < len {
+                out[i + j] += x * y;
+            }
+        }
+    }
+    out
+}
+
+// scales a component's counts so the biggest is one; only ratios matter
+fn normalize(mut stats: Vec<(f64, Vec<f64>)>) -> Vec<(f64, Vec<f64>)> {
+    let max = stats.iter().map(|(n, _)| *n).fold(0.0, f64::max);
+    for (n, rusty) in stats.iter_mut() {
+        *n /= max;
+        for r in rusty.iter_mut() {
+            *r /= max;
+        }
+    }
+    stats
+}
+
+// walks every assignment of the component's variables that agrees with the
+// numbers. Returns, for each possible number of rusts in the component, how
+// many assignments there are and how often each variable holds a rust in
+// them. None if the component is too big to finish
+fn enumerate(
+    component: &[usize],
+    constraints: &[Constraint],
+    var_constraints: &[Vec<usize>],
+    lo: &[u8],
+    hi: &[u8],
+) -> Option<Vec<(f64, Vec<f64>)>> {
+    let mut search = Search {
+        component,
+        var_constraints,
+        lo,
+        hi,
+        needed: constraints.iter().map(|c| c.total).collect(),
+        min_left: constraints
+            .iter()
+            .map(|c| c.vars.iter().map(|&v| lo[v] as i32).sum())
+            .collect(),
+        max_left: constraints
+            .iter()
+            .map(|c| c.vars.iter().map(|&v| hi[v] as i32).sum())
+            .collect(),
+        values: vec![0; component.len()],
+        nodes: 0,
+        budget: SEARCH_BUDGET,
+        rng: None,
+        stats: vec![],
+    };
+    if search.step(0) {
+        Some(search.stats)
+    } else {
+        None
+    }
+}
+
+// Monte Carlo stand-in for `enumerate`: runs many short searches that try
+// values in random order and keeps the first layout each one finds
+fn sample(
+    component: &[usize],
+    constraints: &[Constraint],
+    var_constraints: &[Vec<usize>],
+    lo: &[u8],
+    hi: &[u8],
+) -> Vec<(f64, Vec<f64>)> {
+    let mut search = Search {
+        component,
+        var_constraints,
+        lo,
+        hi,
+        needed: constraints.iter().map(|c| c.total).collect(),
+        min_left: constraints
+            .iter()
+            .map(|c| c.vars.iter().map(|&v| lo[v] as i32).sum())
+            .collect(),
+        max_left: constraints
+            .iter()
+            .map(|c| c.vars.iter().map(|&v| hi[v] as i32).sum())
+            .collect(),
+        values: vec![0; component.len()],
+        nodes: 0,
+        budget: SAMPLE_BUDGET,
+        rng: Some(thread_rng()),
+        stats: vec![],
+    };
+    for _ in 0..SAMPLES {
+        search.nodes = 0;
+        // a sample ends early on its first layout, or on running out of
+        // nodes; either way the counters are back where they started
+        search.step(0);
+    }
+    search.stats
+}
+
+struct Search<'a> {
+    component: &'a [usize],
+    var_constraints: &'a [Vec<usize>],
+    lo: &'a [u8],
+    hi: &'a [u8],
+    // per constraint: rusts still to place, and how few/many the
+    // unassigned cells could still take
+    needed: Vec<i32>,
+    min_left: Vec<i32>,
+    max_left: Vec<i32>,
+    values: Vec<u8>,
+    nodes: usize,
+    budget: usize,
+    // set when sampling: values are tried in random order and the search
+    // stops at the first layout
+    rng: Option<ThreadRng>,
+    stats: Vec<(f64, Vec<f64>)>,
+}
+
+impl<'a> Search<'a> {
+    // false once the budget runs out, or once a sample is found
+    fn step(&mut self, depth: usize) -> bool {
+        self.nodes += 1;
+        if self.nodes > self.budget {
+            return false;
+        }
+        if depth == self.component.len() {
+            let total: usize = self.values.iter().map(|&n| n as usize).sum();
+            while self.stats.len() <= total {
+                self.stats.push((0.0, vec![0.0; self.component.len()]));
+            }
+            let entry = &mut self.stats[total];
+            entry.0 += 1.0;
+            for (i, &n) in self.values.iter().enumerate() {
+                if n > 0 {
+                    entry.1[i] += 1.0;
+                }
+            }
+            return self.rng.is_none();
+        }
+        let v = self.component[depth];
+        let mut order: Vec<u8> = (self.lo[v]..=self.hi[v]).collect();
+        if let Some(rng) = self.rng.as_mut() {
+            order.shuffle(rng);
+        }
+        for value in order {
+            let mut fits = true;
+            for &c in &self.var_constraints[v] {
+                self.needed[c] -= value as i32;
+                self.min_left[c] -= self.lo[v] as i32;
+                self.max_left[c] -= self.hi[v] as i32;
+                if self.needed[c] < self.min_left[c] || self.needed[c] > self.max_left[c] {
+                    fits = false;
+                }
+            }
+            self.values[depth] = value;
+            let finished = !fits || self.step(depth + 1);
+            for &c in &self.var_constraints[v] {
+                self.needed[c] += value as i32;
+                self.min_left[c] += self.lo[v] as i32;
+                self.max_left[c] += self.hi[v] as i32;
+            }
+            if !finished {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{GameEvent, EASY};
+    use crate::text;
+    use crate::variant::{Neighborhood, Variant};
+
+    fn constraint(vars: &[usize], total: i32) -> Constraint {
+        Constraint {
+            vars: vars.to_vec(),
+            total,
+        }
+    }
+
+    #[test]
+    fn one_two_one() {
+        let board = text::parse("...\n*.*\n...\n\n...\n###\n###").unwrap();
+        let analysis = analyze(&board);
+        assert_eq!(analysis.rusts, vec![(0, 1), (2, 1)]);
+        // the middle, and the bottom row now that both rusts are found
+        assert_eq!(analysis.safe, vec![(0, 2), (1, 1), (1, 2), (2, 2)]);
+        assert_eq!(analysis.probabilities[1][0], None);
+        assert_eq!(analysis.probabilities[0][1], Some(1.0));
+    }
+
+    #[test]
+    fn a_found_rust_clears_its_neighbor() {
+        let board = text::parse("*..\n...\n...\n\n#..\n#..\n...").unwrap();
+        let analysis = analyze(&board);
+        assert_eq!(analysis.rusts, vec![(0, 0)]);
+        assert_eq!(analysis.safe, vec![(0, 1)]);
+        assert_eq!(hint(&board), Some(Hint::Safe { x: 0, y: 1 }));
+    }
+
+    #[test]
+    fn nothing_is_safe_in_a_fifty_fifty() {
+        let board = text::parse("*.\n..\n\n##\n..").unwrap();
+        let analysis = analyze(&board);
+        assert!(analysis.safe.is_empty());
+        assert!(analysis.rusts.is_empty());
+        assert_eq!(analysis.probabilities[0][0], Some(0.5));
+        assert_eq!(analysis.probabilities[1][0], Some(0.5));
+        assert!(matches!(
+            hint(&board),
+            Some(Hint::Guess { probability, .. }) if probability == 0.5
+        ));
+    }
+
+    #[test]
+    fn hints_are_never_wrong() {
+        for seed in 0..20 {
+            let mut board = Board::from_seed(EASY, Variant::default(), seed);
+            board.calculate_rust_count();
+            board.clear_rusts_at(4, 4);
+            board.reveal(4, 4);
+            while board.outcome.is_none() {
+                match hint(&board) {
+                    Some(Hint::Safe { x, y }) => {
+                        assert!(!board.cell(x, y).is_rust(), "seed {}", seed);
+                        board.reveal(x, y);
+                    }
+                    Some(Hint::Rust { x, y }) => {
+                        assert!(board.cell(x, y).is_rust(), "seed {}", seed);
+                        board.cycle_flag(x, y);
+                    }
+                    _ => break,
+                }
+            }
+            assert!(
+                !matches!(board.outcome, Some(GameEvent::Failed { .. })),
+                "seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn propagate_settles_forced_cells() {
+        // a 1 on two cells, one of them next to a 0
+        let constraints = vec![constraint(&[0, 1], 1), constraint(&[1], 0)];
+        let (mut lo, mut hi) = (vec![0; 2], vec![1; 2]);
+        propagate(&constraints, &mut lo, &mut hi);
+        assert_eq!((lo, hi), (vec![1, 0], vec![1, 0]));
+
+        // a 3 on two cells that hold up to two rusts each
+        let (mut lo, mut hi) = (vec![0; 2], vec![2; 2]);
+        propagate(&[constraint(&[0, 1], 3)], &mut lo, &mut hi);
+        assert_eq!((lo, hi), (vec![1, 1], vec![2, 2]));
+    }
+
+    #[test]
+    fn enumerate_counts_every_layout() {
+        // the 1-2-1 row: one layout, with two rusts
+        let constraints = vec![
+            constraint(&[0, 1], 1),
+            constraint(&[0, 1, 2], 2),
+            constraint(&[1, 2], 1),
+        ];
+        let var_constraints = vec![vec![0, 1], vec![0, 1, 2], vec![1, 2]];
+        let (lo, hi) = (vec![0; 3], vec![1; 3]);
+        let stats = enumerate(&[0, 1, 2], &constraints, &var_constraints, &lo, &hi).unwrap();
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[2], (1.0, vec![1.0, 0.0, 1.0]));
+        assert!(stats[..2].iter().all(|(n, _)| *n == 0.0));
+
+        // sampling finds the same single layout
+        let sampled = sample(&[0, 1, 2], &constraints, &var_constraints, &lo, &hi);
+        assert_eq!(sampled.len(), 3);
+        assert!(sampled[2].0 > 0.0);
+        assert_eq!(sampled[2].1, vec![sampled[2].0, 0.0, sampled[2].0]);
+
+        // two cells sharing a 1, each holding up to two: (1, 0) and (0, 1)
+        let constraints = vec![constraint(&[0, 1], 1)];
+        let var_constraints = vec![vec![0], vec![0]];
+        let (lo, hi) = (vec![0; 2], vec![2; 2]);
+        let stats = enumerate(&[0, 1], &constraints, &var_constraints, &lo, &hi).unwrap();
+        assert_eq!(stats[1], (2.0, vec![1.0, 1.0]));
+    }
+
+    #[test]
+    fn spreads_count_placements() {
+        let ways = |spreads: &Spreads, rusts| spreads.all(rusts) / spreads.all(0);
+        // four cells: C(4, r) ways, C(3, r) with one cell kept empty
+        let spreads = Spreads::new(4, 1, 4);
+        for (r, &n) in [1.0, 4.0, 6.0, 4.0, 1.0].iter().enumerate() {
+            assert!((ways(&spreads, r) - n).abs() < 1e-9);
+        }
+        assert!((spreads.without_one(2) / spreads.all(0) - 3.0).abs() < 1e-9);
+        assert!(!spreads.possible_without_one(4));
+
+        // two cells of up to two rusts each
+        let spreads = Spreads::new(2, 2, 5);
+        for (r, &n) in [1.0, 2.0, 3.0, 2.0, 1.0].iter().enumerate() {
+            assert!((ways(&spreads, r) - n).abs() < 1e-9);
+        }
+        assert!(!spreads.possible(5));
+        assert!(spreads.possible_without_one(2));
+        assert!(!spreads.possible_without_one(3));
+
+        let empty = Spreads::new(0, 1, 2);
+        assert!(empty.possible(0));
+        assert!(!empty.possible(1));
+    }
+
+    // the chance of each cell holding a rust, worked out the slow way by
+    // trying every layout of the hidden cells against the numbers and the
+    // total
+    fn brute_force(board: &Board) -> Vec<Vec<Option<f64>>> {
+        let hidden: Vec<(usize, usize)> = board
+            .positions()
+            .filter(|&(x, y)| board.cell(x, y).is_hidden())
+            .collect();
+        let max_rusts = board.variant.max_rusts as usize;
+        let mut rusts = vec![vec![0; board.height()]; board.width()];
+        let mut layouts = 0.0;
+        let mut rusty = vec![0.0; hidden.len()];
+        loop {
+            let agrees = hidden.iter().map(|&(x, y)| rusts[x][y]).sum::<usize>()
+                == board.total_rusts()
+                && board.positions().all(|(x, y)| {
+                    let cell = board.cell(x, y);
+                    cell.is_hidden()
+                        || board
+                            .neighbors(x, y)
+                            .iter()
+                            .map(|&(nx, ny)| rusts[nx][ny])
+                            .sum::<usize>()
+                            == cell.rust_count() as usize
+                });
+            if agrees {
+                layouts += 1.0;
+                for (i, &(x, y)) in hidden.iter().enumerate() {
+                    if rusts[x][y] > 0 {
+                        rusty[i] += 1.0;
+                    }
+                }
+            }
+            // on to the next layout, counting in base max_rusts + 1
+            let mut i = 0;
+            while i < hidden.len() && rusts[hidden[i].0][hidden[i].1] == max_rusts {
+                rusts[hidden[i].0][hidden[i].1] = 0;
+                i += 1;
+            }
+            if i == hidden.len() {
+                break;
+            }
+            rusts[hidden[i].0][hidden[i].1] += 1;
+        }
+        let mut probabilities = vec![vec![None; board.height()]; board.width()];
+        for (i, &(x, y)) in hidden.iter().enumerate() {
+            probabilities[x][y] = Some(rusty[i] / layouts);
+        }
+        probabilities
+    }
+
+    fn assert_exact(board: &Board) {
+        let analysis = analyze(board);
+        let expected = brute_force(board);
+        for (x, y) in board.positions() {
+            match (analysis.probabilities[x][y], expected[x][y]) {
+                (None, None) => (),
+                (Some(p), Some(q)) => assert!(
+                    (p as f64 - q).abs() < 1e-5,
+                    "({}, {}) is {} but should be {} on\n{}",
+                    x,
+                    y,
+                    p,
+                    q,
+                    text::write(board)
+                ),
+                (p, q) => panic!("({}, {}) is {:?} but should be {:?}", x, y, p, q),
+            }
+        }
+        for &(x, y) in &analysis.safe {
+            assert_eq!(expected[x][y], Some(0.0));
+        }
+        for &(x, y) in &analysis.rusts {
+            assert_eq!(expected[x][y], Some(1.0));
+        }
+    }
+
+    // small boards from a few seeds, with a different scatter of safe
+    // cells uncovered on each so the numbers overlap in different ways
+    fn opened(size: usize, rusts: usize, variant: Variant) -> Vec<Board> {
+        (0..12)
+            .map(|seed| {
+                let mut board = Board::from_seed((rusts, size), variant, seed);
+                board.calculate_rust_count();
+                let scatter: Vec<(usize, usize)> = board
+                    .positions()
+                    .filter(|&(x, y)| (x + 2 * y + seed as usize).is_multiple_of(4))
+                    .collect();
+                for (x, y) in scatter {
+                    if !board.cell(x, y).is_rust() {
+                        board.reveal(x, y);
+                    }
+                }
+                board
+            })
+            .collect()
+    }
+
+    #[test]
+    fn probabilities_match_every_layout() {
+        for board in opened(4, 3, Variant::default()) {
+            assert_exact(&board);
+        }
+        let board = text::parse("*...\n....\n..*.\n.*..\n\n##..\n#...\n#.##\n####");
+        assert_exact(&board.unwrap());
+    }
+
+    #[test]
+    fn probabilities_match_every_layout_when_wrapping() {
+        for neighborhood in &[Neighborhood::Moore, Neighborhood::Knight] {
+            let variant = Variant {
+                wrapping: true,
+                neighborhood: *neighborhood,
+                ..Variant::default()
+            };
+            for board in opened(4, 3, variant) {
+                assert_exact(&board);
+            }
+        }
+    }
+
+    #[test]
+    fn probabilities_match_every_layout_when_stacked() {
+        let variant = Variant {
+            max_rusts: 2,
+            ..Variant::default()
+        };
+        for board in opened(3, 3, variant) {
+            assert_exact(&board);
+        }
+        assert_exact(&text::parse("variant rusts=3\n3..\n...\n.2.\n\n#..\n...\n###").unwrap());
+    }
+
+    #[test]
+    fn untouched_cells_share_what_is_left() {
+        // one rust beside the 1, the other in any of the three cells
+        // nothing touches
+        let board = text::parse("*...*.\n\n#.####").unwrap();
+        let analysis = analyze(&board);
+        assert_eq!(analysis.probabilities[0][0], Some(0.5));
+        assert_eq!(analysis.probabilities[2][0], Some(0.5));
+        for x in 3..6 {
+            let p = analysis.probabilities[x][0].unwrap();
+            assert!((p - 1.0 / 3.0).abs() < 1e-6);
+        }
+
+        // two rusts in two cells of two: (2, 0), (1, 1) or (0, 2), so each
+        // cell holds some in two of the three
+        let board = text::parse("variant rusts=2\n2.\n\n##").unwrap();
+        let p = analyze(&board).probabilities[0][0].unwrap();
+        assert!((p - 2.0 / 3.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,361 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::storage;
+use crate::variant::{Neighborhood, Variant};
+
+const STATS_FILE: &str = "stats.txt";
+
+/// Clicks a player made on the board during one game.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Clicks {
+    pub left: usize,
+    pub right: usize,
+    pub chord: usize,
+}
+
+impl Clicks {
+    pub fn total(&self) -> usize {
+        self.left + self.right + self.chord
+    }
+}
+
+/// One finished game, as kept in the stats file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+    /// Seconds since the unix epoch.
+    pub finished_at: u64,
+    pub difficulty: (usize, usize),
+    /// Games on other rules are kept apart from classic ones. Lines from
+    /// before variants were recorded are classic.
+    pub variant: Variant,
+    pub won: bool,
+    pub seconds: f64,
+    pub three_bv: usize,
+    pub clicks: Clicks,
+    pub hints: usize,
+}
+
+impl GameRecord {
+    pub fn new(
+        difficulty: (usize, usize),
+        variant: Variant,
+        won: bool,
+        seconds: f64,
+        three_bv: usize,
+        clicks: Clicks,
+        hints: usize,
+    ) -> Self {
+        let finished_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        GameRecord {
+            finished_at,
+            difficulty,
+            variant,
+            won,
+            seconds,
+            three_bv,
+            clicks,
+            hints,
+        }
+    }
+
+    pub fn three_bv_per_second(&self) -> f64 {
+        if self.seconds > 0.0 {
+            self.three_bv as f64 / self.seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// 3BV over clicks made; 100% means not a single wasted click.
+    pub fn efficiency(&self) -> f64 {
+        if self.clicks.total() > 0 {
+            self.three_bv as f64 / self.clicks.total() as f64
+        } else {
+            0.0
+        }
+    }
+
+    // one line of space separated key=value pairs, so the file stays easy
+    // to read and to grep
+    pub fn to_line(&self) -> String {
+        format!(
+            "at={} rusts={} size={} variant={}-{}-{} won={} time={:.3} 3bv={} left={} right={} \
+             chord={} hints={}",
+            self.finished_at,
+            self.difficulty.0,
+            self.difficulty.1,
+            if self.variant.wrapping {
+                "wrap"
+            } else {
+                "flat"
+            },
+            self.variant.neighborhood.name(),
+            self.variant.max_rusts,
+            self.won as u8,
+            self.seconds,
+            self.three_bv,
+            self.clicks.left,
+            self.clicks.right,
+            self.clicks.chord,
+            self.hints
+        )
+    }
+
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut record = GameRecord {
+            finished_at: 0,
+            difficulty: (0, 0),
+            variant: Variant::default(),
+            won: false,
+            seconds: 0.0,
+            three_bv: 0,
+            clicks: Clicks::default(),
+            hints: 0,
+        };
+        for pair in line.split_whitespace() {
+            let mut parts = pair.splitn(2, '=');
+            let (key, value) = (parts.next()?, parts.next()?);
+            match key {
+                "at" => record.finished_at = value.parse().ok()?,
+                "rusts" => record.difficulty.0 = value.parse().ok()?,
+                "size" => record.difficulty.1 = value.parse().ok()?,
+                "variant" => record.variant = parse_variant(value)?,
+                "won" => record.won = value == "1",
+                "time" => record.seconds = value.parse().ok()?,
+                "3bv" => record.three_bv = value.parse().ok()?,
+                "left" => record.clicks.left = value.parse().ok()?,
+                "right" => record.clicks.right = value.parse().ok()?,
+                "chord" => record.clicks.chord = value.parse().ok()?,
+                "hints" => record.hints = value.parse().ok()?,
+                // written by a newer version, keep going
+                _ => (),
+            }
+        }
+        Some(record)
+    }
+}
+
+// the variant as `to_line` writes it, such as `wrap-knight-2`
+fn parse_variant(word: &str) -> Option<Variant> {
+    match word.split('-').collect::<Vec<_>>()[..] {
+        [wrapping, neighborhood, max_rusts] => Some(Variant {
+            wrapping: match wrapping {
+                "wrap" => true,
+                "flat" => false,
+                _ => return None,
+            },
+            neighborhood: Neighborhood::from_name(neighborhood)?,
+            max_rusts: max_rusts.parse().ok().filter(|max| (1..=9).contains(max))?,
+        }),
+        _ => None,
+    }
+}
+
+/// Adds a finished game to the end of the stats file.
+pub fn append(record: &GameRecord) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(storage::data_file(STATS_FILE)?)?;
+    writeln!(file, "{}", record.to_line())
+}
+
+/// Every game in the stats file, oldest first. Lines that don't parse are
+/// skipped rather than losing the whole history.
+pub fn load() -> io::Result<Vec<GameRecord>> {
+    let contents = match fs::read_to_string(storage::data_file(STATS_FILE)?) {
+        Ok(contents) => contents,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    Ok(contents.lines().filter_map(GameRecord::from_line).collect())
+}
+
+/// Lifetime totals for one difficulty and variant, worked out from the game
+/// history.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    pub played: usize,
+    pub won: usize,
+    pub lost: usize,
+    /// Wins in a row up to the latest game.
+    pub current_streak: usize,
+    pub best_streak: usize,
+    /// Only won games have a time worth comparing.
+    pub best_time: Option<f64>,
+    pub median_time: Option<f64>,
+    pub average_three_bv_per_second: Option<f64>,
+}
+
+impl Summary {
+    /// Totals over the games played at `difficulty` on `variant`, given
+    /// oldest first.
+    pub fn new(records: &[GameRecord], difficulty: (usize, usize), variant: Variant) -> Self {
+        let mut summary = Summary::default();
+        let mut times = vec![];
+        let mut speed = 0.0;
+        for record in records
+            .iter()
+            .filter(|r| r.difficulty == difficulty && r.variant == variant)
+        {
+            summary.played += 1;
+            if record.won {
+                summary.won += 1;
+                summary.current_streak += 1;
+                summary.best_streak = summary.best_streak.max(summary.current_streak);
+                times.push(record.seconds);
+                speed += record.three_bv_per_second();
+            } else {
+                summary.lost += 1;
+                summary.current_streak = 0;
+            }
+        }
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        if !times.is_empty() {
+            let mid = times.len() / 2;
+            summary.best_time = Some(times[0]);
+            summary.median_time = Some(if times.len() % 2 == 0 {
+                (times[mid - 1] + times[mid]) / 2.0
+            } else {
+                times[mid]
+            });
+            summary.average_three_bv_per_second = Some(speed / times.len() as f64);
+        }
+        summary
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        if self.played > 0 {
+            self.won as f64 / self.played as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The latest `count` games at `difficulty` on `variant`, oldest first.
+pub fn recent(
+    records: &[GameRecord],
+    difficulty: (usize, usize),
+    variant: Variant,
+    count: usize,
+) -> Vec<&GameRecord> {
+    let mut recent: Vec<&GameRecord> = records
+        .iter()
+        .rev()
+        .filter(|r| r.difficulty == difficulty && r.variant == variant)
+        .take(count)
+        .collect();
+    recent.reverse();
+    recent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASY: (usize, usize) = (10, 9);
+
+    fn game(difficulty: (usize, usize), won: bool, seconds: f64) -> GameRecord {
+        GameRecord {
+            finished_at: 1_700_000_000,
+            difficulty,
+            variant: Variant::default(),
+            won,
+            seconds,
+            three_bv: 20,
+            clicks: Clicks {
+                left: 15,
+                right: 4,
+                chord: 1,
+            },
+            hints: 0,
+        }
+    }
+
+    #[test]
+    fn lines_round_trip() {
+        let record = game(EASY, true, 12.5);
+        assert_eq!(GameRecord::from_line(&record.to_line()), Some(record));
+        // keys from a newer version are ignored
+        let line = format!("{} medal=gold", game(EASY, false, 3.0).to_line());
+        assert_eq!(GameRecord::from_line(&line), Some(game(EASY, false, 3.0)));
+
+        let mut stacked = game(EASY, true, 9.0);
+        stacked.variant = Variant {
+            wrapping: true,
+            neighborhood: Neighborhood::Knight,
+            max_rusts: 2,
+        };
+        assert!(stacked.to_line().contains(" variant=wrap-knight-2 "));
+        assert_eq!(GameRecord::from_line(&stacked.to_line()), Some(stacked));
+    }
+
+    #[test]
+    fn lines_without_a_variant_are_classic() {
+        let line = "at=1700000000 rusts=10 size=9 won=1 time=30.000 3bv=20 left=15 right=4 \
+                    chord=1 hints=0";
+        assert_eq!(GameRecord::from_line(line), Some(game(EASY, true, 30.0)));
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let good = game(EASY, true, 30.0).to_line();
+        let contents = format!(
+            "{}\nat=soon rusts=10\nrusts=10 size\ntime=-- won=1\n3bv=-4\nvariant=flat-hex-1\n{}\n",
+            good, good
+        );
+        let records: Vec<GameRecord> = contents.lines().filter_map(GameRecord::from_line).collect();
+        assert_eq!(
+            records,
+            vec![game(EASY, true, 30.0), game(EASY, true, 30.0)]
+        );
+    }
+
+    #[test]
+    fn summary_counts_streaks_and_times() {
+        let records = vec![
+            game(EASY, true, 40.0),
+            game(EASY, true, 20.0),
+            game(EASY, true, 35.0),
+            game(EASY, false, 5.0),
+            // other difficulties don't count
+            game((40, 16), true, 1.0),
+            game(EASY, true, 25.0),
+        ];
+        // nor do games on other rules, even in between
+        let mut mixed = vec![];
+        for record in &records {
+            let mut other = record.clone();
+            other.variant.wrapping = true;
+            other.won = !other.won;
+            mixed.push(other);
+            mixed.push(record.clone());
+        }
+        let summary = Summary::new(&mixed, EASY, Variant::default());
+        assert_eq!((summary.played, summary.won, summary.lost), (5, 4, 1));
+        assert_eq!(summary.current_streak, 1);
+        assert_eq!(summary.best_streak, 3);
+        assert_eq!(summary.best_time, Some(20.0));
+        assert_eq!(summary.median_time, Some(30.0));
+        assert_eq!(summary.win_rate(), 0.8);
+
+        let lost = Summary::new(&[game(EASY, false, 5.0)], EASY, Variant::default());
+        assert_eq!(lost.best_time, None);
+        assert_eq!(lost.median_time, None);
+        assert_eq!(
+            Summary::new(&records, (99, 30), Variant::default()),
+            Summary::default()
+        );
+        let classic: Vec<f64> = recent(&mixed, EASY, Variant::default(), 3)
+            .iter()
+            .map(|r| r.seconds)
+            .collect();
+        assert_eq!(classic, vec![35.0, 5.0, 25.0]);
+    }
+}
@@ -0,0 +1,21 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Where a file the game keeps between runs lives. That is
+/// `$RUSTSWEEPER_DATA_DIR` if set, otherwise `.rustsweeper` in the home
+/// directory. The directory is created on first use.
+pub fn data_file(name: &str) -> io::Result<PathBuf> {
+    let dir = match env::var_os("RUSTSWEEPER_DATA_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = env::var_os("HOME")
+                .or_else(|| env::var_os("USERPROFILE"))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+            PathBuf::from(home).join(".rustsweeper")
+        }
+    };
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(name))
+}
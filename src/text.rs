@@ -0,0 +1,275 @@
+//! A plain text format for boards, for writing puzzles and tests by hand and
+//! for exporting a game in progress.
+//!
+//! ```text
+//! // lines starting with two slashes are comments
+//! variant wrap knight rusts=2
+//! ..*.
+//! .2..
+//! ....
+//!
+//! ##..
+//! #F..
+//! ....
+//! ```
+//!
+//! The optional `variant` line comes first and lists any of `wrap`,
+//! `moore`, `knight`, `radius2` and `rusts=N` (the most rusts one cell can
+//! hold); anything left out keeps the classic default.
+//!
+//! Then comes the layout, one line per row and one character per cell:
+//! `.` is safe, `*` is a single rust and a digit is a stack of that many.
+//!
+//! After a blank line an optional state layer of the same size may follow:
+//! `.` is uncovered, `#` is still hidden, `F` is hidden under one flag, a
+//! digit is hidden under that many and `?` is hidden under a question mark.
+//! Without it every cell starts hidden.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::board::Board;
+use crate::cell::Cell;
+use crate::variant::{Neighborhood, Variant};
+
+/// Why a board could not be read, and on which line (counted from 1).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Reads a board written in the format above.
+pub fn parse(text: &str) -> Result<Board, ParseError> {
+    let mut variant = Variant::default();
+    let mut layout: Vec<(usize, &str)> = vec![];
+    let mut state: Vec<(usize, &str)> = vec![];
+    let mut seen_blank = false;
+    for (n, line) in text.lines().enumerate() {
+        let n = n + 1;
+        let line = line.trim();
+        if line.starts_with("//") {
+            continue;
+        }
+        if line.is_empty() {
+            // splits the layout from the state layer
+            seen_blank |= !layout.is_empty();
+            continue;
+        }
+        if let Some(options) = line.strip_prefix("variant") {
+            if !layout.is_empty() {
+                return Err(ParseError::new(n, "the variant line must come first"));
+            }
+            variant = parse_variant(n, options)?;
+        } else if seen_blank {
+            state.push((n, line));
+        } else {
+            layout.push((n, line));
+        }
+    }
+    if layout.is_empty() {
+        return Err(ParseError::new(1, "no cells"));
+    }
+
+    let width = layout[0].1.chars().count();
+    let height = layout.len();
+    for &(n, row) in layout.iter().chain(state.iter()) {
+        if row.chars().count() != width {
+            return Err(ParseError::new(
+                n,
+                format!("expected {} cells, found {}", width, row.chars().count()),
+            ));
+        }
+    }
+    if !state.is_empty() && state.len() != height {
+        return Err(ParseError::new(
+            state.last().map_or(1, |&(n, _)| n),
+            format!("expected {} state rows, found {}", height, state.len()),
+        ));
+    }
+
+    let mut cells = vec![Cell::new(0); width * height];
+    for (y, &(n, row)) in layout.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let rusts = match c {
+                '.' => 0,
+                '*' => 1,
+                _ => c
+                    .to_digit(10)
+                    .ok_or_else(|| ParseError::new(n, format!("unknown cell '{}'", c)))?
+                    as u8,
+            };
+            if rusts > variant.max_rusts {
+                return Err(ParseError::new(
+                    n,
+                    format!(
+                        "{} rusts in one cell, the variant allows {}",
+                        rusts, variant.max_rusts
+                    ),
+                ));
+            }
+            cells[x * height + y].set_rusts(rusts);
+        }
+    }
+    for (y, &(n, row)) in state.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let cell = &mut cells[x * height + y];
+            match c {
+                '.' => cell.set_hidden(false),
+                '#' => (),
+                'F' => cell.set_flags(1),
+                '?' => cell.set_marked(true),
+                _ => {
+                    let flags = c
+                        .to_digit(10)
+                        .ok_or_else(|| ParseError::new(n, format!("unknown state '{}'", c)))?
+                        as u8;
+                    if flags > variant.max_rusts {
+                        return Err(ParseError::new(
+                            n,
+                            format!(
+                                "{} flags on one cell, the variant allows {}",
+                                flags, variant.max_rusts
+                            ),
+                        ));
+                    }
+                    cell.set_flags(flags);
+                }
+            }
+        }
+    }
+    Ok(Board::from_cells(width, height, cells, variant))
+}
+
+fn parse_variant(n: usize, options: &str) -> Result<Variant, ParseError> {
+    let mut variant = Variant::default();
+    for option in options.split_whitespace() {
+        match option {
+            "wrap" => variant.wrapping = true,
+            _ if option.starts_with("rusts=") => {
+                variant.max_rusts = option["rusts=".len()..]
+                    .parse()
+                    .ok()
+                    .filter(|&max| (1..=9).contains(&max))
+                    .ok_or_else(|| ParseError::new(n, format!("bad option '{}'", option)))?;
+            }
+            _ => match Neighborhood::from_name(option) {
+                Some(neighborhood) => variant.neighborhood = neighborhood,
+                None => return Err(ParseError::new(n, format!("unknown option '{}'", option))),
+            },
+        }
+    }
+    Ok(variant)
+}
+
+/// Writes a board in the format above. The variant line is left out for
+/// classic boards and the state layer for boards nobody has touched yet,
+/// so `parse(&write(board))` gives the same board back.
+pub fn write(board: &Board) -> String {
+    let mut text = String::new();
+    let variant = board.variant;
+    if variant != Variant::default() {
+        text.push_str("variant");
+        if variant.wrapping {
+            text.push_str(" wrap");
+        }
+        text.push_str(&format!(
+            " {} rusts={}\n",
+            variant.neighborhood.name(),
+            variant.max_rusts
+        ));
+    }
+    let (width, height) = (board.width(), board.height());
+    for y in 0..height {
+        for x in 0..width {
+            text.push(match board.cell(x, y).rusts() {
+                0 => '.',
+                1 => '*',
+                n => (b'0' + n) as char,
+            });
+        }
+        text.push('\n');
+    }
+    let touched = board
+        .cells()
+        .iter()
+        .any(|cell| !cell.is_hidden() || cell.is_flagged() || cell.is_marked());
+    if touched {
+        text.push('\n');
+        for y in 0..height {
+            for x in 0..width {
+                let cell = board.cell(x, y);
+                text.push(match (cell.is_hidden(), cell.flags()) {
+                    (false, _) => '.',
+                    (true, 0) if cell.is_marked() => '?',
+                    (true, 0) => '#',
+                    (true, 1) => 'F',
+                    (true, n) => (b'0' + n) as char,
+                });
+            }
+            text.push('\n');
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> ParseError {
+        parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn ragged_rows_are_rejected() {
+        assert_eq!(error("...\n..\n...").line, 2);
+        assert_eq!(error("..\n..\n\n..\n.").line, 5);
+        // a state layer with too few rows
+        assert_eq!(error("..\n..\n\n..").line, 4);
+    }
+
+    #[test]
+    fn unknown_glyphs_are_rejected() {
+        let e = error("..\n.x");
+        assert_eq!(e.line, 2);
+        assert_eq!(e.message, "unknown cell 'x'");
+        assert_eq!(error("..\n..\n\n.#\n!.").message, "unknown state '!'");
+        assert_eq!(error("variant hex\n..").message, "unknown option 'hex'");
+        assert_eq!(error("..\nvariant wrap").line, 2);
+    }
+
+    #[test]
+    fn stacks_above_the_variant_are_rejected() {
+        assert_eq!(error("2.").line, 1);
+        assert!(parse("variant rusts=2\n2.").is_ok());
+        assert_eq!(error("variant rusts=2\n3.").line, 2);
+        assert_eq!(error("*.\n\n2.").line, 3);
+        assert!(parse("variant rusts=3\n3.\n\n3.").is_ok());
+        assert!(parse("variant rusts=10\n..").is_err());
+        assert!(parse("variant rusts=0\n..").is_err());
+    }
+
+    #[test]
+    fn written_boards_read_back() {
+        let text = "variant wrap knight rusts=2\n..*.\n.2..\n\n#?..\n#F..\n";
+        assert_eq!(write(&parse(text).unwrap()), text);
+    }
+}
@@ -0,0 +1,130 @@
+/// Which cells count as touching each other. Every rule that looks at
+/// neighbors (counting, flood fill, chording) goes through this.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Neighborhood {
+    /// The classic eight surrounding cells.
+    #[default]
+    Moore,
+    /// The eight cells a chess knight could jump to.
+    Knight,
+    /// Every cell within two steps in both directions.
+    Radius2,
+}
+
+const MOORE: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+const KNIGHT: [(i32, i32); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+const RADIUS_2: [(i32, i32); 24] = [
+    (-2, -2),
+    (-2, -1),
+    (-2, 0),
+    (-2, 1),
+    (-2, 2),
+    (-1, -2),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (-1, 2),
+    (0, -2),
+    (0, -1),
+    (0, 1),
+    (0, 2),
+    (1, -2),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (1, 2),
+    (2, -2),
+    (2, -1),
+    (2, 0),
+    (2, 1),
+    (2, 2),
+];
+
+impl Neighborhood {
+    /// Relative positions of the neighbors of a cell.
+    pub fn offsets(self) -> &'static [(i32, i32)] {
+        match self {
+            Neighborhood::Moore => &MOORE,
+            Neighborhood::Knight => &KNIGHT,
+            Neighborhood::Radius2 => &RADIUS_2,
+        }
+    }
+
+    /// The neighborhood after this one, for cycling through them in the UI.
+    pub fn next(self) -> Self {
+        match self {
+            Neighborhood::Moore => Neighborhood::Knight,
+            Neighborhood::Knight => Neighborhood::Radius2,
+            Neighborhood::Radius2 => Neighborhood::Moore,
+        }
+    }
+
+    /// How the neighborhood is written in board files and on the wire.
+    pub fn name(self) -> &'static str {
+        match self {
+            Neighborhood::Moore => "moore",
+            Neighborhood::Knight => "knight",
+            Neighborhood::Radius2 => "radius2",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "moore" => Some(Neighborhood::Moore),
+            "knight" => Some(Neighborhood::Knight),
+            "radius2" => Some(Neighborhood::Radius2),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Neighborhood::Moore => "MOORE",
+            Neighborhood::Knight => "KNIGHT",
+            Neighborhood::Radius2 => "RADIUS 2",
+        }
+    }
+}
+
+/// Rule variations a `Board` can be generated with. The default is classic
+/// minesweeper.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Variant {
+    /// Connect opposite edges of the board, so every cell has a full set of
+    /// neighbors.
+    pub wrapping: bool,
+    pub neighborhood: Neighborhood,
+    /// How many rusts can pile up in one cell. Counts on the board add up
+    /// every rust, and a cell takes up to this many flags.
+    pub max_rusts: u8,
+}
+
+impl Default for Variant {
+    fn default() -> Self {
+        Variant {
+            wrapping: false,
+            neighborhood: Neighborhood::default(),
+            max_rusts: 1,
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::board::{Board, GameEvent};
+use crate::player::Move;
+
+/// How players earn points in a versus game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scoring {
+    /// A point for every safe cell a move uncovers. Flags are free notes
+    /// that don't end the turn, and uncovering a rust loses the game.
+    Reveals,
+    /// Flagging a cell claims it as a rust and the board checks the claim
+    /// straight away: a rust scores a point per rust and earns another
+    /// move, a safe cell gets uncovered and the turn passes.
+    Flags,
+}
+
+impl Scoring {
+    pub fn label(self) -> &'static str {
+        match self {
+            Scoring::Reveals => "VS REVEALS",
+            Scoring::Flags => "VS FLAGS",
+        }
+    }
+}
+
+/// How a versus game ended. Players are numbered 0 and 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Finish {
+    Won(usize),
+    Draw,
+}
+
+/// Two players taking turns on one board.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Versus {
+    pub scoring: Scoring,
+    pub scores: [usize; 2],
+    /// Whose move it is.
+    pub turn: usize,
+    /// Confirmed rusts as (x, y, player who claimed them).
+    pub claims: Vec<(usize, usize, usize)>,
+    pub finish: Option<Finish>,
+}
+
+impl Versus {
+    pub fn new(scoring: Scoring) -> Self {
+        Versus {
+            scoring,
+            scores: [0, 0],
+            turn: 0,
+            claims: vec![],
+            finish: None,
+        }
+    }
+
+    /// Plays `mv` for the player whose turn it is, scoring it and passing
+    /// the turn on as the rules say. Moves that change nothing are ignored
+    /// and keep the turn. Returns the finish once the game is decided.
+    pub fn play(&mut self, board: &mut Board, mv: Move) -> Option<Finish> {
+        if self.finish.is_some() || board.outcome.is_some() {
+            return self.finish;
+        }
+        let player = self.turn;
+        let before = board.revealed_safe();
+        match (self.scoring, mv) {
+            (Scoring::Reveals, Move::Flag { x, y }) => {
+                board.cycle_flag(x, y);
+                return None;
+            }
+            (Scoring::Flags, Move::Flag { x, y }) => {
+                let cell = board.cell(x, y);
+                // every flag is a confirmed claim, so they stay put
+                if !cell.is_hidden() || cell.is_flagged() {
+                    return None;
+                }
+                if cell.is_rust() {
+                    let rusts = cell.rusts();
+                    while board.cell(x, y).flags() < rusts {
+                        board.cycle_flag(x, y);
+                    }
+                    self.scores[player] += rusts as usize;
+                    self.claims.push((x, y, player));
+                } else {
+                    board.reveal(x, y);
+                    self.turn = 1 - player;
+                }
+            }
+            (scoring, mv) => {
+                let event = mv.apply(board);
+                let uncovered = board.revealed_safe() - before;
+                if uncovered == 0 && event.is_none() {
+                    return None;
+                }
+                if let Some(GameEvent::Failed { .. }) = event {
+                    self.finish = Some(Finish::Won(1 - player));
+                    return self.finish;
+                }
+                if scoring == Scoring::Reveals {
+                    self.scores[player] += uncovered;
+                }
+                self.turn = 1 - player;
+            }
+        }
+        self.finish = self.decided(board);
+        self.finish
+    }
+
+    // over once every point is taken, or as soon as the player behind
+    // could not catch up even by taking all that are left. Rusts nobody
+    // claimed before the last safe cell was uncovered can't be taken any
+    // more, so a cleared board is decided on the scores so far
+    fn decided(&self, board: &Board) -> Option<Finish> {
+        let total = match self.scoring {
+            Scoring::Reveals => board.safe_cells(),
+            Scoring::Flags => board.total_rusts(),
+        };
+        let [a, b] = self.scores;
+        let left = if board.outcome.is_some() {
+            0
+        } else {
+            total.saturating_sub(a + b)
+        };
+        if a > b + left {
+            Some(Finish::Won(0))
+        } else if b > a + left {
+            Some(Finish::Won(1))
+        } else if left == 0 {
+            Some(Finish::Draw)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text;
+
+    #[test]
+    fn clearing_the_board_decides_a_flags_game() {
+        let mut board = text::parse("**..").unwrap();
+        let mut versus = Versus::new(Scoring::Flags);
+        assert_eq!(versus.play(&mut board, Move::Flag { x: 0, y: 0 }), None);
+        assert_eq!(versus.turn, 0);
+        // uncovers both safe cells with one rust still unclaimed
+        let finish = versus.play(&mut board, Move::Reveal { x: 3, y: 0 });
+        assert_eq!(board.outcome, Some(GameEvent::Solved));
+        assert_eq!(finish, Some(Finish::Won(0)));
+        assert_eq!(versus.finish, finish);
+    }
+
+    #[test]
+    fn clearing_the_board_level_is_a_draw() {
+        let mut board = text::parse("*..").unwrap();
+        let mut versus = Versus::new(Scoring::Flags);
+        assert_eq!(
+            versus.play(&mut board, Move::Reveal { x: 2, y: 0 }),
+            Some(Finish::Draw)
+        );
+    }
+}
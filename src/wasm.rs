@@ -0,0 +1,126 @@
+//! The engine for the browser, behind the `wasm` feature:
+//!
+//! ```sh
+//! wasm-pack build --target web -- --no-default-features --features wasm
+//! ```
+//!
+//! ```js
+//! const game = new Game('{"difficulty": "medium", "seed": 7}');
+//! const { outcome, cells } = JSON.parse(game.reveal(4, 4));
+//! ```
+//!
+//! Options, states and move results are JSON, the same as the `rpc`
+//! methods of the same names take and return.
+
+use serde_json::{json, Map, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::board::Board;
+use crate::player::Move;
+use crate::rpc;
+use crate::solver::{self, Hint};
+use crate::text;
+
+/// One game, played the way the `rpc` binary plays it.
+#[wasm_bindgen]
+pub struct Game {
+    board: Board,
+    safe_start: bool,
+}
+
+#[wasm_bindgen]
+impl Game {
+    /// Starts a game from `new_game` options, such as
+    /// `{"difficulty": "hard", "seed": 3, "wrap": true}` or
+    /// `{"board": "..*\n..."}`. An empty string is an easy random game.
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: &str) -> Result<Game, JsValue> {
+        let options = if options.trim().is_empty() {
+            Map::new()
+        } else {
+            match serde_json::from_str(options) {
+                Ok(Value::Object(options)) => options,
+                Ok(_) => return Err(JsValue::from_str("options must be an object")),
+                Err(e) => return Err(JsValue::from_str(&e.to_string())),
+            }
+        };
+        let (board, safe_start) =
+            rpc::new_board(&options).map_err(|e| JsValue::from_str(&e.message))?;
+        Ok(Game { board, safe_start })
+    }
+
+    pub fn width(&self) -> usize {
+        self.board.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.board.height()
+    }
+
+    pub fn reveal(&mut self, x: usize, y: usize) -> Result<String, JsValue> {
+        self.play(Move::Reveal { x, y })
+    }
+
+    pub fn flag(&mut self, x: usize, y: usize) -> Result<String, JsValue> {
+        self.play(Move::Flag { x, y })
+    }
+
+    pub fn chord(&mut self, x: usize, y: usize) -> Result<String, JsValue> {
+        self.play(Move::Chord { x, y })
+    }
+
+    /// The whole board as the player sees it.
+    pub fn state(&self) -> String {
+        rpc::state(&self.board).to_string()
+    }
+
+    /// The solver's suggestion as `{"kind": "safe" | "rust" | "guess", "x",
+    /// "y"}`, with the `probability` of a rust for a guess, or `null`.
+    pub fn hint(&self) -> String {
+        match solver::hint(&self.board) {
+            Some(Hint::Safe { x, y }) => json!({"kind": "safe", "x": x, "y": y}),
+            Some(Hint::Rust { x, y }) => json!({"kind": "rust", "x": x, "y": y}),
+            Some(Hint::Guess { x, y, probability }) => {
+                json!({"kind": "guess", "x": x, "y": y, "probability": probability})
+            }
+            None => Value::Null,
+        }
+        .to_string()
+    }
+
+    /// Everything the solver can work out: the proven `safe` and `rusts`
+    /// cells as `[x, y]` pairs and each cell's chance of a rust as rows,
+    /// `null` where the cell is uncovered.
+    pub fn analysis(&self) -> String {
+        let analysis = solver::analyze(&self.board);
+        let probabilities: Value = (0..self.board.height())
+            .map(|y| {
+                (0..self.board.width())
+                    .map(|x| json!(analysis.probabilities[x][y]))
+                    .collect::<Value>()
+            })
+            .collect();
+        json!({
+            "safe": analysis.safe,
+            "rusts": analysis.rusts,
+            "probabilities": probabilities,
+        })
+        .to_string()
+    }
+
+    /// The board in the `text` format.
+    #[wasm_bindgen(js_name = toText)]
+    pub fn to_text(&self) -> String {
+        text::write(&self.board)
+    }
+}
+
+impl Game {
+    fn play(&mut self, mv: Move) -> Result<String, JsValue> {
+        let (x, y) = mv.position();
+        if x >= self.board.width() || y >= self.board.height() {
+            return Err(JsValue::from_str(&format!("no cell at {} {}", x, y)));
+        }
+        Ok(rpc::play(&mut self.board, self.safe_start, mv).to_string())
+    }
+}
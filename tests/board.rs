@@ -0,0 +1,262 @@
+// Properties of the board rules, checked on random boards of every shape
+// from a single cell up, with every variant.
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::collections::HashSet;
+
+use minesweeper::board::{Board, GameEvent};
+use minesweeper::cell::Cell;
+use minesweeper::player::Move;
+use minesweeper::text;
+use minesweeper::variant::{Neighborhood, Variant};
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+fn variant() -> impl Strategy<Value = Variant> {
+    let neighborhood = prop_oneof![
+        Just(Neighborhood::Moore),
+        Just(Neighborhood::Knight),
+        Just(Neighborhood::Radius2),
+    ];
+    (any::<bool>(), neighborhood, 1u8..=3).prop_map(|(wrapping, neighborhood, max_rusts)| Variant {
+        wrapping,
+        neighborhood,
+        max_rusts,
+    })
+}
+
+// a hidden board up to 12 cells a side, rows and columns of one included,
+// with about one cell in five holding rusts
+fn board() -> impl Strategy<Value = Board> {
+    (1usize..=12, 1usize..=12, variant())
+        .prop_flat_map(|(width, height, variant)| {
+            let rusts = prop_oneof![4 => Just(0), 1 => 1..=variant.max_rusts];
+            (Just(width), Just(variant), vec(rusts, width * height))
+        })
+        .prop_map(|(width, variant, rusts)| {
+            let height = rusts.len() / width;
+            let cells = rusts.into_iter().map(Cell::new).collect();
+            Board::from_cells(width, height, cells, variant)
+        })
+}
+
+fn moves(count: usize) -> impl Strategy<Value = Vec<(u8, usize, usize)>> {
+    vec((0u8..3, any::<usize>(), any::<usize>()), 0..count)
+}
+
+fn to_move(board: &Board, (kind, x, y): (u8, usize, usize)) -> Move {
+    let (x, y) = (x % board.width(), y % board.height());
+    match kind {
+        0 => Move::Reveal { x, y },
+        1 => Move::Flag { x, y },
+        _ => Move::Chord { x, y },
+    }
+}
+
+// whether `b` is one of `a`'s neighbors, straight from the definition
+// rather than through `Board::neighbors`
+fn touches(board: &Board, a: (usize, usize), b: (usize, usize)) -> bool {
+    let (width, height) = (board.width() as i32, board.height() as i32);
+    a != b
+        && board.variant.neighborhood.offsets().iter().any(|&(i, j)| {
+            let (x, y) = (a.0 as i32 + i, a.1 as i32 + j);
+            if board.variant.wrapping {
+                (x.rem_euclid(width), y.rem_euclid(height)) == (b.0 as i32, b.1 as i32)
+            } else {
+                (x, y) == (b.0 as i32, b.1 as i32)
+            }
+        })
+}
+
+fn positions(board: &Board) -> Vec<(usize, usize)> {
+    (0..board.width())
+        .flat_map(|x| (0..board.height()).map(move |y| (x, y)))
+        .collect()
+}
+
+// the counters and the outcome the board should have, worked out from its
+// cells alone
+fn check_consistent(board: &Board) -> Result<(), TestCaseError> {
+    let cells = board.cells();
+    let safe = cells.iter().filter(|c| !c.is_rust()).count();
+    let revealed = cells
+        .iter()
+        .filter(|c| !c.is_rust() && !c.is_hidden())
+        .count();
+    let flags: usize = cells.iter().map(|c| c.flags() as usize).sum();
+    prop_assert_eq!(board.safe_cells(), safe);
+    prop_assert_eq!(board.revealed_safe(), revealed);
+    prop_assert_eq!(board.flags_placed(), flags);
+    for cell in cells {
+        prop_assert!(cell.rusts() <= board.variant.max_rusts);
+        prop_assert!(cell.flags() <= board.variant.max_rusts);
+        prop_assert!(!cell.is_flagged() || cell.is_hidden());
+    }
+    let uncovered_rusts = cells
+        .iter()
+        .filter(|c| c.is_rust() && !c.is_hidden())
+        .count();
+    match board.outcome {
+        Some(GameEvent::Failed { x, y }) => {
+            prop_assert!(board.cell(x, y).is_rust() && !board.cell(x, y).is_hidden());
+            // the fatal rust is the only one uncovered
+            prop_assert_eq!(uncovered_rusts, 1);
+        }
+        Some(GameEvent::Solved) => {
+            prop_assert_eq!(revealed, safe);
+            prop_assert_eq!(uncovered_rusts, 0);
+        }
+        None => {
+            prop_assert_eq!(uncovered_rusts, 0);
+            prop_assert!(revealed < safe || safe == 0);
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn counts_match_brute_force(board in board()) {
+        for a in positions(&board) {
+            let expected: u8 = positions(&board)
+                .into_iter()
+                .filter(|&b| touches(&board, a, b))
+                .map(|(x, y)| board.cell(x, y).rusts())
+                .sum();
+            prop_assert_eq!(board.cell(a.0, a.1).rust_count(), expected, "at {:?}", a);
+        }
+    }
+
+    #[test]
+    fn neighbors_are_symmetric(board in board()) {
+        for a in positions(&board) {
+            for b in board.neighbors(a.0, a.1) {
+                prop_assert!(board.neighbors(b.0, b.1).contains(&a));
+                prop_assert!(touches(&board, a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn flood_fill_opens_the_zero_region_and_its_border(
+        mut board in board(),
+        flags in vec(any::<bool>(), 144),
+        start in any::<usize>(),
+    ) {
+        for (n, (x, y)) in positions(&board).into_iter().enumerate() {
+            if flags[n] && n % 3 == 0 {
+                board.cycle_flag(x, y);
+            }
+        }
+        // spread through hidden, unflagged zeros, opening whatever they touch
+        let spreads = |(x, y): (usize, usize)| {
+            let cell = board.cell(x, y);
+            cell.rust_count() == 0 && !cell.is_rust() && !cell.is_flagged()
+        };
+        // any zero will do, picked from a random point onwards
+        let all = positions(&board);
+        let start = all.iter().cycle().skip(start % all.len()).take(all.len()).find(|&&p| spreads(p));
+        prop_assume!(start.is_some());
+        let start = *start.unwrap();
+        let mut region = HashSet::new();
+        let mut stack = vec![start];
+        region.insert(start);
+        while let Some(a) = stack.pop() {
+            for b in positions(&board) {
+                if touches(&board, a, b) && spreads(b) && region.insert(b) {
+                    stack.push(b);
+                }
+            }
+        }
+        let mut expected = region.clone();
+        for &a in &region {
+            for b in positions(&board) {
+                if touches(&board, a, b) && !board.cell(b.0, b.1).is_flagged() {
+                    expected.insert(b);
+                }
+            }
+        }
+
+        let before = board.clone();
+        board.flood_fill(start.0, start.1);
+        for (x, y) in positions(&board) {
+            let (was, now) = (before.cell(x, y), board.cell(x, y));
+            prop_assert_eq!(now.is_hidden(), !expected.contains(&(x, y)), "at {:?}", (x, y));
+            prop_assert!(now.is_hidden() || !now.is_rust());
+            prop_assert!(now.is_hidden() || !was.is_flagged());
+            prop_assert_eq!(now.flags(), was.flags());
+        }
+        check_consistent(&board)?;
+    }
+
+    #[test]
+    fn moves_keep_the_board_consistent(mut board in board(), moves in moves(40)) {
+        check_consistent(&board)?;
+        for mv in moves {
+            let mv = to_move(&board, mv);
+            let outcome = board.outcome;
+            let before = text::write(&board);
+            let event = mv.apply(&mut board);
+            check_consistent(&board)?;
+            if outcome.is_some() {
+                // a finished game stays as it was
+                prop_assert_eq!(event, None);
+                prop_assert_eq!(board.outcome, outcome);
+                prop_assert_eq!(text::write(&board), before);
+            } else {
+                // the move that ends the game is the one that reports it
+                prop_assert_eq!(event, board.outcome);
+            }
+        }
+    }
+
+    #[test]
+    fn first_click_is_always_safe(
+        size in 1usize..=20,
+        density in 0usize..=100,
+        variant in variant(),
+        seed in any::<u64>(),
+        at in (any::<usize>(), any::<usize>()),
+    ) {
+        let room = size * size * variant.max_rusts as usize;
+        let rusts = room * density / 100;
+        let mut board = Board::from_seed((rusts, size), variant, seed);
+        board.calculate_rust_count();
+        prop_assert_eq!(board.total_rusts(), rusts);
+        let (x, y) = (at.0 % size, at.1 % size);
+        board.clear_rusts_at(x, y);
+        prop_assert_eq!(board.total_rusts(), rusts);
+        check_consistent(&board)?;
+        // only a board with no room left elsewhere can't be made safe
+        let spare = room - variant.max_rusts as usize;
+        prop_assume!(rusts <= spare);
+        let event = board.reveal(x, y);
+        let lost = matches!(event, Some(GameEvent::Failed { .. }));
+        prop_assert!(!lost, "lost on the first click at {:?}", (x, y));
+        check_consistent(&board)?;
+    }
+
+    #[test]
+    fn revealing_every_safe_cell_wins(board in board()) {
+        let mut board = board;
+        prop_assume!(board.safe_cells() > 0);
+        for (x, y) in positions(&board) {
+            if !board.cell(x, y).is_rust() {
+                board.reveal(x, y);
+            }
+            let cleared = board.revealed_safe() == board.safe_cells();
+            prop_assert_eq!(board.outcome.is_some(), cleared);
+        }
+        prop_assert_eq!(board.outcome, Some(GameEvent::Solved));
+    }
+
+    #[test]
+    fn text_round_trips(mut board in board(), moves in moves(10)) {
+        for mv in moves {
+            to_move(&board, mv).apply(&mut board);
+        }
+        let again = text::parse(&text::write(&board)).unwrap();
+        prop_assert_eq!(text::write(&again), text::write(&board));
+        prop_assert_eq!(again.outcome, board.outcome);
+    }
+}
@@ -0,0 +1,355 @@
+// The multiplayer protocol, line by line and against a real server on
+// localhost.
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::io::{self, BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+use minesweeper::board::{Board, GameEvent, EASY};
+use minesweeper::net::{self, Client, ClientMessage, NetMode, ServerMessage};
+use minesweeper::player::Move;
+use minesweeper::variant::{Neighborhood, Variant};
+
+#[test]
+fn server_messages_round_trip() {
+    let messages = vec![
+        ServerMessage::Welcome {
+            player: 1,
+            mode: NetMode::Race,
+        },
+        ServerMessage::Board {
+            width: 9,
+            height: 9,
+            rusts: 10,
+            variant: Variant::default(),
+        },
+        ServerMessage::Board {
+            width: 12,
+            height: 3,
+            rusts: 7,
+            variant: Variant {
+                wrapping: true,
+                neighborhood: Neighborhood::Knight,
+                max_rusts: 3,
+            },
+        },
+        ServerMessage::Open {
+            x: 1,
+            y: 2,
+            count: 3,
+        },
+        ServerMessage::Flags {
+            x: 4,
+            y: 5,
+            flags: 2,
+        },
+        ServerMessage::Rust {
+            x: 6,
+            y: 7,
+            rusts: 1,
+        },
+        ServerMessage::Over {
+            player: 0,
+            event: GameEvent::Solved,
+        },
+        ServerMessage::Over {
+            player: 2,
+            event: GameEvent::Failed { x: 3, y: 4 },
+        },
+        ServerMessage::Joined {
+            player: 1,
+            name: "bob smith".to_owned(),
+        },
+        ServerMessage::Left { player: 1 },
+        ServerMessage::Progress {
+            player: 1,
+            revealed: 5,
+            safe: 71,
+        },
+        ServerMessage::Winner { player: 0 },
+        ServerMessage::Error {
+            message: "no cell at 9 9".to_owned(),
+        },
+        ServerMessage::Cursor { x: 1.5, y: 2.25 },
+        ServerMessage::Clock {
+            seconds: 12.5,
+            running: true,
+        },
+        ServerMessage::Clock {
+            seconds: 0.0,
+            running: false,
+        },
+    ];
+    for message in messages {
+        let line = message.to_line();
+        assert_eq!(ServerMessage::from_line(&line), Some(message), "{}", line);
+    }
+    // servers from before the variant was sent mean a classic board
+    assert_eq!(
+        ServerMessage::from_line("BOARD 9 9 10"),
+        Some(ServerMessage::Board {
+            width: 9,
+            height: 9,
+            rusts: 10,
+            variant: Variant::default(),
+        })
+    );
+}
+
+#[test]
+fn client_messages_round_trip() {
+    let messages = vec![
+        ClientMessage::Hello {
+            name: "alice".to_owned(),
+        },
+        ClientMessage::Move(Move::Reveal { x: 1, y: 2 }),
+        ClientMessage::Move(Move::Flag { x: 3, y: 4 }),
+        ClientMessage::Move(Move::Chord { x: 5, y: 6 }),
+        ClientMessage::NewGame,
+    ];
+    for message in messages {
+        let line = message.to_line();
+        assert_eq!(ClientMessage::from_line(&line), Some(message), "{}", line);
+    }
+}
+
+#[test]
+fn malformed_lines_are_rejected() {
+    for line in &["", "JUMP 1 2", "REVEAL 1", "FLAG x 2", "CHORD -1 2"] {
+        assert_eq!(ClientMessage::from_line(line), None, "{}", line);
+    }
+    for line in &[
+        "",
+        "NONSENSE 1 2",
+        "WELCOME 0 solo",
+        "WELCOME",
+        "BOARD 9 9",
+        "BOARD 9 9 10 wrap",
+        "BOARD 9 9 10 round moore 1",
+        "BOARD 9 9 10 flat hex 1",
+        "BOARD 9 9 10 flat moore 0",
+        "OPEN 1 2",
+        "OPEN a b c",
+        "OVER 0 exploded",
+        "OVER 0 failed 3",
+        "LEFT",
+        "PROGRESS 1 2",
+        "CURSOR 1.5",
+        "CLOCK",
+        // numbers that would wrap or overflow what a cell can hold
+        "OPEN 0 0 300",
+        "OPEN 0 0 217",
+        "OPEN 0 0 -1",
+        "FLAGS 0 0 10",
+        "RUST 0 0 300",
+        "RUST 0 0 10",
+        "BOARD 9 9 10 flat moore 10",
+    ] {
+        assert_eq!(ServerMessage::from_line(line), None, "{}", line);
+    }
+}
+
+#[test]
+fn messages_off_the_board_are_ignored() {
+    let mut board = net::blank_board(3, 3, Variant::default());
+    for line in &[
+        "OPEN 999 999 3",
+        "OPEN 0 3 1",
+        "OPEN 0 0 9",
+        "FLAGS 3 0 1",
+        "FLAGS 0 0 2",
+        "RUST 0 99 1",
+        "RUST 0 0 2",
+    ] {
+        let message = ServerMessage::from_line(line).unwrap();
+        net::apply(&mut board, &message);
+    }
+    assert!(board
+        .cells()
+        .iter()
+        .all(|cell| cell.is_hidden() && cell.flags() == 0 && cell.rusts() == 0));
+    assert_eq!(board.flags_placed(), 0);
+
+    net::apply(&mut board, &ServerMessage::from_line("OPEN 2 2 8").unwrap());
+    assert!(!board.cell(2, 2).is_hidden());
+    assert_eq!(board.cell(2, 2).rust_count(), 8);
+}
+
+// the server binary, killed when the test is done with it however it ends
+struct Server {
+    child: Child,
+    addr: String,
+}
+
+impl Server {
+    fn start(mode: &str) -> Server {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_server"))
+            .args(["--port", "0", "--mode", mode, "--seed", "5"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        // "coop server on 127.0.0.1:PORT"
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        // keep reading so the server's later log lines have somewhere to go
+        thread::spawn(move || io::copy(&mut stdout, &mut io::sink()));
+        let addr = line.trim().rsplit(' ').next().unwrap().to_owned();
+        Server { child, addr }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// waits until `client` has heard something matching `done`, returning
+// everything it heard on the way
+fn receive(client: &Client, done: impl Fn(&ServerMessage) -> bool) -> Vec<ServerMessage> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut messages = vec![];
+    while Instant::now() < deadline {
+        let arrived = client.poll();
+        let finished = arrived.iter().any(&done);
+        messages.extend(arrived);
+        if finished {
+            return messages;
+        }
+        sleep(Duration::from_millis(10));
+    }
+    panic!("timed out, heard {:?}", messages);
+}
+
+#[test]
+fn coop_session_on_localhost() {
+    let server = Server::start("coop");
+    let mut alice = Client::connect(&server.addr, "alice").unwrap();
+    let hello = receive(&alice, |m| matches!(m, ServerMessage::Joined { .. }));
+    assert_eq!(
+        hello[0],
+        ServerMessage::Welcome {
+            player: 0,
+            mode: NetMode::Coop,
+        }
+    );
+    let mut board = match hello[1] {
+        ServerMessage::Board {
+            width,
+            height,
+            rusts,
+            variant,
+        } => {
+            assert_eq!((width, height, rusts), (9, 9, 10));
+            net::blank_board(width, height, variant)
+        }
+        ref other => panic!("expected a board, got {:?}", other),
+    };
+
+    let bob = Client::connect(&server.addr, "bob").unwrap();
+    receive(&bob, |m| {
+        matches!(m, ServerMessage::Joined { player: 1, .. })
+    });
+    receive(&alice, |m| {
+        matches!(m, ServerMessage::Joined { player: 1, .. })
+    });
+
+    // the first reveal is always safe, and both players see it
+    alice
+        .send(&ClientMessage::Move(Move::Reveal { x: 4, y: 4 }))
+        .unwrap();
+    let opened = |m: &ServerMessage| matches!(m, ServerMessage::Open { x: 4, y: 4, .. });
+    for message in receive(&alice, opened) {
+        net::apply(&mut board, &message);
+    }
+    assert!(!board.cell(4, 4).is_hidden());
+    receive(&bob, opened);
+
+    alice
+        .send(&ClientMessage::Move(Move::Reveal { x: 99, y: 0 }))
+        .unwrap();
+    receive(&alice, |m| matches!(m, ServerMessage::Error { .. }));
+
+    drop(bob);
+    receive(&alice, |m| *m == ServerMessage::Left { player: 1 });
+}
+
+#[test]
+fn race_session_on_localhost() {
+    let server = Server::start("race");
+    let mut alice = Client::connect(&server.addr, "alice").unwrap();
+    let hello = receive(&alice, |m| matches!(m, ServerMessage::Joined { .. }));
+    assert_eq!(
+        hello[0],
+        ServerMessage::Welcome {
+            player: 0,
+            mode: NetMode::Race,
+        }
+    );
+    let mut bob = Client::connect(&server.addr, "bob").unwrap();
+    receive(&bob, |m| {
+        matches!(m, ServerMessage::Joined { player: 1, .. })
+    });
+    receive(&alice, |m| {
+        matches!(m, ServerMessage::Joined { player: 1, .. })
+    });
+
+    // the server deals from the seed it was given, so the test can know
+    // where the rusts are once the first reveal has been made safe
+    let mut layout = Board::from_seed(EASY, Variant::default(), 5);
+    layout.calculate_rust_count();
+    layout.clear_rusts_at(4, 4);
+
+    // alice's moves open cells on her board only, bob just hears how far
+    // she has got
+    alice
+        .send(&ClientMessage::Move(Move::Reveal { x: 4, y: 4 }))
+        .unwrap();
+    let heard = receive(&alice, |m| {
+        matches!(m, ServerMessage::Progress { player: 0, .. })
+    });
+    assert!(heard.contains(&ServerMessage::Open {
+        x: 4,
+        y: 4,
+        count: layout.cell(4, 4).rust_count(),
+    }));
+    let heard = receive(&bob, |m| {
+        matches!(m, ServerMessage::Progress { player: 0, .. })
+    });
+    assert!(!heard
+        .iter()
+        .any(|m| matches!(m, ServerMessage::Open { .. })));
+    match heard.last() {
+        Some(&ServerMessage::Progress { revealed, safe, .. }) => {
+            assert!(revealed > 0);
+            assert_eq!(safe, layout.safe_cells());
+        }
+        other => panic!("expected progress, got {:?}", other),
+    }
+
+    // bob's board is still untouched, so his first move opens it up too
+    bob.send(&ClientMessage::Move(Move::Reveal { x: 4, y: 4 }))
+        .unwrap();
+    receive(&bob, |m| {
+        matches!(m, ServerMessage::Open { x: 4, y: 4, .. })
+    });
+
+    for (x, y) in layout.positions() {
+        if !layout.cell(x, y).is_rust() {
+            alice
+                .send(&ClientMessage::Move(Move::Reveal { x, y }))
+                .unwrap();
+        }
+    }
+    let won = |m: &ServerMessage| *m == ServerMessage::Winner { player: 0 };
+    let heard = receive(&alice, won);
+    assert!(heard.contains(&ServerMessage::Over {
+        player: 0,
+        event: GameEvent::Solved,
+    }));
+    receive(&bob, won);
+}
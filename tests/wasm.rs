@@ -0,0 +1,82 @@
+// The browser API, run headless in node:
+//
+//     cargo test --target wasm32-unknown-unknown --no-default-features --features wasm
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use minesweeper::wasm::Game;
+use serde_json::Value;
+use wasm_bindgen_test::*;
+
+// a rust in the top left corner and three ones around it
+const CORNER: &str = "*..\n...\n...\n";
+
+fn json(text: &str) -> Value {
+    serde_json::from_str(text).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn same_seed_same_game() {
+    let options = r#"{"difficulty": "medium", "seed": 7}"#;
+    let (mut a, mut b) = (Game::new(options).unwrap(), Game::new(options).unwrap());
+    assert_eq!(a.reveal(3, 5).unwrap(), b.reveal(3, 5).unwrap());
+    assert_eq!(a.state(), b.state());
+}
+
+#[wasm_bindgen_test]
+fn first_reveal_is_safe() {
+    for seed in 0..20 {
+        let mut game =
+            Game::new(&format!(r#"{{"difficulty": "hard", "seed": {}}}"#, seed)).unwrap();
+        let result = json(&game.reveal(0, 0).unwrap());
+        assert_ne!(result["outcome"], "failed");
+        assert!(!result["cells"].as_array().unwrap().is_empty());
+    }
+}
+
+#[wasm_bindgen_test]
+fn text_boards_play_as_written() {
+    let board = serde_json::json!({ "board": CORNER }).to_string();
+    let mut game = Game::new(&board).unwrap();
+    assert_eq!((game.width(), game.height()), (3, 3));
+    let result = json(&game.reveal(0, 0).unwrap());
+    assert_eq!(result["outcome"], "failed");
+    assert_eq!(result["fatal"], serde_json::json!({"x": 0, "y": 0}));
+}
+
+#[wasm_bindgen_test]
+fn flood_fill_wins() {
+    let board = serde_json::json!({ "board": CORNER }).to_string();
+    let mut game = Game::new(&board).unwrap();
+    let result = json(&game.reveal(2, 2).unwrap());
+    assert_eq!(result["outcome"], "solved");
+    let state = json(&game.state());
+    assert_eq!(state["revealed"], 8);
+    assert_eq!(state["cells"][1][1]["count"], 1);
+    assert_eq!(state["cells"][0][0]["rusts"], 1);
+}
+
+#[wasm_bindgen_test]
+fn solver_finds_the_rust() {
+    let board = serde_json::json!({ "board": "*..\n...\n...\n\n##.\n#..\n...\n" }).to_string();
+    let game = Game::new(&board).unwrap();
+    let analysis = json(&game.analysis());
+    assert_eq!(analysis["rusts"], serde_json::json!([[0, 0]]));
+    assert_eq!(json(&game.hint())["kind"], "safe");
+}
+
+#[wasm_bindgen_test]
+fn bad_input_is_an_error() {
+    assert!(Game::new(r#"{"difficulty": "impossible"}"#).is_err());
+    assert!(Game::new("not json").is_err());
+    let mut game = Game::new("").unwrap();
+    assert!(game.reveal(100, 0).is_err());
+}
+
+#[wasm_bindgen_test]
+fn text_round_trip() {
+    let board = serde_json::json!({ "board": CORNER }).to_string();
+    let mut game = Game::new(&board).unwrap();
+    game.flag(0, 0).unwrap();
+    let again = serde_json::json!({ "board": game.to_text() }).to_string();
+    assert_eq!(Game::new(&again).unwrap().state(), game.state());
+}